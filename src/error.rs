@@ -0,0 +1,96 @@
+//! Error type returned by every public Blade API.
+
+use std::fmt;
+use polars::prelude::PolarsError;
+
+pub type Result<T> = std::result::Result<T, BladeError>;
+
+#[derive(Debug)]
+pub enum BladeError {
+    Io(std::io::Error),                  // filesystem failures while reading or writing
+    Schema(String),                      // input does not match the expected columns / types
+    MalformedValue {
+        column: String,                  // column holding the value
+        row: usize,                      // row of the value in the input dataset
+        message: String,                 // what is wrong with the value
+    },
+    Codec(String),                       // a codec could not encode or decode a column
+    Format(String),                      // not a valid BLADE archive, or unusable input
+    Integrity(String),                   // checksum mismatch, overlapping or duplicate data
+}
+
+impl BladeError {
+
+    pub fn malformed(column: &str, row: usize, message: impl Into<String>) -> Self {
+        BladeError::MalformedValue {
+            column: column.to_string(),
+            row,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for BladeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BladeError::Io(e) => write!(f, "io error: {}", e),
+            BladeError::Schema(msg) => write!(f, "schema error: {}", msg),
+            BladeError::MalformedValue { column, row, message } => {
+                write!(f, "malformed value in column `{}` at row {}: {}", column, row, message)
+            },
+            BladeError::Codec(msg) => write!(f, "codec error: {}", msg),
+            BladeError::Format(msg) => write!(f, "format error: {}", msg),
+            BladeError::Integrity(msg) => write!(f, "integrity error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for BladeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BladeError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for BladeError {
+    fn from(e: std::io::Error) -> Self {
+        BladeError::Io(e)
+    }
+}
+
+impl From<PolarsError> for BladeError {
+    fn from(e: PolarsError) -> Self {
+        match e {
+            PolarsError::IO { error, .. } => BladeError::Io(std::io::Error::new(error.kind(), error.to_string())),
+            PolarsError::ColumnNotFound(msg)
+            | PolarsError::SchemaFieldNotFound(msg)
+            | PolarsError::SchemaMismatch(msg) => BladeError::Schema(msg.to_string()),
+            other => BladeError::Format(other.to_string()),
+        }
+    }
+}
+
+impl From<serde_json::Error> for BladeError {
+    fn from(e: serde_json::Error) -> Self {
+        BladeError::Format(format!("invalid manifest: {}", e))
+    }
+}
+
+impl From<arrow::error::ArrowError> for BladeError {
+    fn from(e: arrow::error::ArrowError) -> Self {
+        BladeError::Schema(e.to_string())
+    }
+}
+
+/// Lets Blade errors flow through polars APIs, eg: the `scan_blade` anonymous scan.
+impl From<BladeError> for PolarsError {
+    fn from(e: BladeError) -> Self {
+        match e {
+            BladeError::Io(error) => PolarsError::from(error),
+            BladeError::Schema(msg) => PolarsError::SchemaMismatch(msg.into()),
+            other => PolarsError::ComputeError(other.to_string().into()),
+        }
+    }
+}
@@ -10,4 +10,5 @@
 //! 3) Write to new file (writer.rs)
 
 // mods
+pub mod error;
 pub mod transfers;
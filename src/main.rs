@@ -1,17 +1,217 @@
 use std::env;
-use std::path::PathBuf;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use owo_colors::OwoColorize;
 // use polars::prelude::*;
 
 // use blade::transfers::*;
+use blade::error::{BladeError, Result};
 use blade::transfers::transfers::Transfer;
+use blade::transfers::compaction::{compact_directory, merge_archives};
+use blade::transfers::integrity::fsck_directory;
+use blade::transfers::bundle::DictionaryBundle;
+use blade::transfers::manifest::StreamEncoding;
+use blade::transfers::options::CompressOptions;
+use blade::transfers::output::{compress_directory, ensure_not_input, OutputOptions};
+use blade::transfers::record::transfer_record_schema;
+use blade::transfers::writer::atomic_write;
+use blade::transfers::export::{export_transfers, ExportFormat, ExportOptions};
+use blade::transfers::analytics::TransferAnalytics;
+use polars::prelude::{CsvWriter, SerWriter};
 
 fn main() {
-    // get args 
-    let args: Vec<String> = env::args().collect();
+    // get args; `--` flags are options, the rest positional
+    let (flags, args): (Vec<String>, Vec<String>) = env::args().partition(|arg| arg.starts_with("--"));
+
+    // compression options: `--huffman` entropy codes index / count streams,
+    // `--reorder` sorts rows within each block for compression, `--registry=<file>`
+    // takes address ids from a registry shared across archives, `--bundle=<file>`
+    // starts dictionaries from a trained bundle
+    let mut options = CompressOptions::new();
+    if flags.iter().any(|flag| flag == "--huffman") {
+        options.entropy_coding = StreamEncoding::Huffman;
+    }
+    options.reorder_rows = flags.iter().any(|flag| flag == "--reorder");
+    options.address_registry = flags.iter().find_map(|flag| flag.strip_prefix("--registry=")).map(PathBuf::from);
+    options.dictionary_bundle = flags.iter().find_map(|flag| flag.strip_prefix("--bundle=")).map(PathBuf::from);
+
+    let result = output_options(&flags)
+        .and_then(|output| Ok((output, export_options(&flags)?)))
+        .and_then(|(output, export)| run(&args, &options, &output, &export));
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Output options: `--output-dir=<dir>` writes archives there instead of beside
+/// their input, `--naming=<template>` names them, eg: `{chain}__{dataset}__{start}_to_{end}.blade`,
+/// `--mirror` recreates the input tree of a batch under the output directory, and
+/// `--overwrite=fail|skip|replace` handles existing archives
+fn output_options(flags: &[String]) -> Result<OutputOptions> {
+    let mut output = OutputOptions::new();
+    output.directory = flags.iter().find_map(|flag| flag.strip_prefix("--output-dir=")).map(PathBuf::from);
+    if let Some(naming) = flags.iter().find_map(|flag| flag.strip_prefix("--naming=")) {
+        output.naming = naming.to_string();
+    }
+    output.mirror = flags.iter().any(|flag| flag == "--mirror");
+    if let Some(policy) = flags.iter().find_map(|flag| flag.strip_prefix("--overwrite=")) {
+        output.overwrite = policy.parse()?;
+    }
+    Ok(output)
+}
+
+/// Export options of decompress: `--format=parquet|csv|ndjson|ipc` (else from the
+/// output extension), `--compression=<codec>[:<level>]` of parquet / IPC output, and
+/// `--columns=<a,b,...>` exporting only those columns
+fn export_options(flags: &[String]) -> Result<ExportOptions> {
+    let mut export = ExportOptions::new();
+    export.format = flags.iter().find_map(|flag| flag.strip_prefix("--format=")).map(str::parse).transpose()?;
+    export.compression = flags.iter().find_map(|flag| flag.strip_prefix("--compression=")).map(str::parse).transpose()?;
+    export.columns = flags.iter().find_map(|flag| flag.strip_prefix("--columns="))
+        .map(|columns| columns.split(',').map(|column| column.trim().to_string()).collect());
+    Ok(export)
+}
+
+fn run(args: &[String], options: &CompressOptions, output: &OutputOptions, export: &ExportOptions) -> Result<()> {
+    // positional argument as a path, erroring with usage when it is missing
+    let path = |i: usize| args.get(i).map(PathBuf::from)
+        .ok_or_else(|| BladeError::Format("usage: blade [--huffman] [--reorder] [--registry=<file>] [--bundle=<file>] [--output-dir=<dir>] [--naming=<template>] [--mirror] [--overwrite=fail|skip|replace] <file|directory|-> [output|-] | decompress [--format=parquet|csv|ndjson|ipc] [--compression=<codec>[:<level>]] [--columns=<a,b,...>] <archive|-> <output|-> | append <archive> <chunk> | merge <output> <archive>... | compact <directory> [max_rows] | fsck <directory> | train <bundle> <file>... | analyze <archive> blocks|tokens [n]|pairs [n]|senders <start> <end> | schema".to_string()));
 
     // instantiate transfer
     let mut transfers = Transfer::new();
-    let _o = transfers.compress(&PathBuf::from(&args[1])).unwrap();
+    transfers.options = options.clone();
+    transfers.output = output.clone();
+    let command = path(1)?;
+    match command.to_string_lossy().as_ref() {
+        // append a new chunk to an existing archive: `blade append <archive> <chunk>`
+        "append" => transfers.append(&path(2)?, &path(3)?)?,
+        // merge adjacent archives into one: `blade merge <output> <archive>...`
+        "merge" => {
+            let output = path(2)?;
+            let archives: Vec<PathBuf> = args.iter().skip(3).map(PathBuf::from).collect();
+            merge_archives(&archives, &output, options)?;
+        },
+        // compact a directory of archives: `blade compact <directory> [max_rows]`
+        "compact" => {
+            let max_rows: usize = match args.get(3) {
+                Some(rows) => rows.parse().map_err(|_| BladeError::Format(format!("invalid max_rows `{}`", rows)))?,
+                None => 10_000_000,
+            };
+            for archive in compact_directory(&path(2)?, max_rows, options, output)? {
+                eprintln!("{} {:?}", "[COMPACTED]".bright_cyan(), archive);
+            }
+        },
+        // verify the checksums of every archive under a directory: `blade fsck <directory>`
+        "fsck" => {
+            let damaged = fsck_directory(&path(2)?)?;
+            if !damaged.is_empty() {
+                return Err(BladeError::Integrity(format!("{} damaged archive(s)", damaged.len())));
+            }
+        },
+        // train a dictionary bundle on sample files: `blade train <bundle> <file>...`
+        "train" => {
+            let bundle = path(2)?;
+            let samples: Vec<PathBuf> = args.iter().skip(3).map(PathBuf::from).collect();
+            if samples.is_empty() {
+                return Err(BladeError::Format("train needs at least one sample file".to_string()));
+            }
+            let bundle = DictionaryBundle::train(&bundle, &samples)?;
+            bundle.save()?;
+            let amounts: usize = bundle.amounts.iter().map(|(_, amounts)| amounts.len()).sum();
+            eprintln!("{} {} files → {} addresses, {} amounts over {} tokens", "[TRAIN]".bright_cyan(), samples.len(), bundle.addresses.len(), amounts, bundle.amounts.len());
+        },
+        // answer a query on the encoded streams, printed as CSV:
+        // `blade analyze <archive> blocks|tokens [n]|pairs [n]|senders <start> <end>`
+        "analyze" => {
+            let analytics = TransferAnalytics::open(&path(2)?)?;
+            let number = |i: usize| args.get(i)
+                .map(|arg| arg.parse::<u64>().map_err(|_| BladeError::Format(format!("invalid number `{}`", arg))))
+                .transpose();
+            let mut df = match path(3)?.to_string_lossy().as_ref() {
+                "blocks" => analytics.transfers_per_block()?,
+                "tokens" => analytics.top_tokens(number(4)?.map_or(usize::MAX, |n| n as usize))?,
+                "pairs" => analytics.top_address_pairs(number(4)?.map_or(10, |n| n as usize))?,
+                "senders" => {
+                    let block = |i: usize| number(i)?.and_then(|block| u32::try_from(block).ok())
+                        .ok_or_else(|| BladeError::Format("senders needs a <start> and <end> block".to_string()));
+                    println!("{}", analytics.unique_senders(block(4)?, block(5)?)?);
+                    return Ok(());
+                },
+                query => return Err(BladeError::Format(format!("unknown query `{}`, expected blocks, tokens, pairs or senders", query))),
+            };
+            let mut sink = BufWriter::new(io::stdout().lock());
+            CsvWriter::new(&mut sink).finish(&mut df)?;
+            sink.flush()?;
+        },
+        // print the JSON Schema of a serialized TransferRecord: `blade schema`
+        "schema" => println!("{}", serde_json::to_string_pretty(&transfer_record_schema())?),
+        // decode an archive and export it, by default as a cryo parquet file:
+        // `blade decompress [--format=..] [--compression=..] [--columns=..] <archive|-> <output|->`
+        "decompress" => {
+            let archive = path(2)?;
+            let target = path(3)?;
+            let df = match (is_stdio(&archive), export.stored_columns()) {
+                (true, Some(columns)) => transfers.decompress_reader_projection(io::stdin().lock(), &columns)?,
+                (true, None) => transfers.decompress_reader(io::stdin().lock())?,
+                (false, Some(columns)) => transfers.decompress_projection(&archive, &columns)?,
+                (false, None) => transfers.decompress(&archive)?,
+            };
+            let mut export = export.clone();
+            export.format = export.format.or_else(|| ExportFormat::from_path(&target));
+            write_output(&target, Some(&archive), output, |sink| export_transfers(sink, &df, &export))?;
+        },
+        // compress where the output options place the archive, or into `target`;
+        // a directory compresses every cryo transfers file below it, `-` reads
+        // stdin, and a stdin input writes stdout unless given a target
+        _ => match (is_stdio(&command), args.get(2).map(PathBuf::from)) {
+            (false, None) if command.is_dir() => {
+                let written = compress_directory(&command, options, output)?;
+                eprintln!("{} {} archives written below {:?}", "[BATCH]".bright_cyan(), written.len(), output.directory.as_deref().unwrap_or(&command));
+            },
+            (false, None) => {
+                let start_time = Instant::now();
+                match transfers.compress_into(&command, None)? {
+                    Some(archive) => eprintln!("{} {:?} in {:.2?}", "[END]".bright_cyan(), archive, start_time.elapsed()),
+                    None => eprintln!("{} {:?} has an archive already", "[SKIP]".yellow(), command),
+                }
+            },
+            (true, target) => {
+                let target = target.unwrap_or_else(|| PathBuf::from("-"));
+                write_output(&target, None, output, |sink| transfers.compress_reader(io::stdin().lock(), sink))?;
+            },
+            (false, Some(target)) => write_output(&target, Some(&command), output, |sink| transfers.compress_file_to_writer(&command, sink))?,
+        },
+    }
+    Ok(())
+}
 
+/// `-` stands for stdin as an input and stdout as an output.
+fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
 
+/// Run `write` against stdout for `-`, else against a file at `target`, written
+/// atomically and subject to the overwrite policy; `target` may never be `input`.
+fn write_output(target: &Path, input: Option<&Path>, output: &OutputOptions, write: impl FnOnce(&mut dyn Write) -> Result<()>) -> Result<()> {
+    if is_stdio(target) {
+        let mut sink = BufWriter::new(io::stdout().lock());
+        write(&mut sink)?;
+        sink.flush()?;
+        return Ok(());
+    }
+    if let Some(input) = input {
+        ensure_not_input(target, input)?;
+    }
+    if !output.overwrite.allows(target)? {
+        eprintln!("{:?} exists, skipped", target);
+        return Ok(());
+    }
+    atomic_write(target, |file| {
+        let mut sink = BufWriter::new(file);
+        write(&mut sink)?;
+        sink.flush()?;
+        Ok(())
+    })
 }
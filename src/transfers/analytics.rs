@@ -0,0 +1,287 @@
+// Compressed-domain analytics over BLADE archives.
+// Block and token runs already count the transfers of each value, and dictionary
+// ids and vocabulary ids can be counted or collected without resolving them to
+// addresses. `TransferAnalytics` reads only the block_number, erc20 and address
+// streams of an archive and answers its queries on those encoded streams; an
+// address is formatted only for the ids of the result rows.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use polars::prelude::*;
+use crate::error::{BladeError, Result};
+use super::compression::address::decode_address;
+use super::compression::address_vocabulary::VOCABULARY_COLUMNS;
+use super::compression::{
+    DictionaryCompressedAddressSeries, DictionaryCompressedErc20Series, RLECompressedBlockNumberSeries,
+    RLECompressedErc20Series, SharedVocabularyCompressedAddressSeries, Streams,
+};
+use super::manifest::Manifest;
+use super::reader::parquet_projection_reader;
+
+/// Columns whose streams the analytics read.
+pub const ANALYTICS_COLUMNS: [&str; 4] = ["block_number", "erc20", "from_address", "to_address"];
+
+pub struct TransferAnalytics {
+    pub manifest: Manifest,     // manifest of the archive
+    pub streams: Streams,       // encoded streams of `ANALYTICS_COLUMNS`
+}
+
+impl TransferAnalytics {
+
+    /// Read the block_number, erc20 and address streams of a BLADE archive;
+    /// hashes, indexes and amounts are never read.
+    pub fn open(filepath: &PathBuf) -> Result<Self> {
+        let columns: Vec<String> = ANALYTICS_COLUMNS.iter().map(|column| column.to_string()).collect();
+        let (manifest, streams) = parquet_projection_reader(filepath, &columns)?;
+        Ok(Self {
+            manifest,
+            streams,
+        })
+    }
+
+    /// Transfers of every block, in block order, summed from the block runs.
+    pub fn transfers_per_block(&self) -> Result<DataFrame> {
+        let blocks = self.blocks()?;
+        let mut transfers: BTreeMap<Option<u32>, u64> = BTreeMap::new();
+        for (block, count) in blocks.values.iter().zip(blocks.counts.iter()) {
+            *transfers.entry(*block).or_insert(0) += *count as u64;
+        }
+        Ok(DataFrame::new(vec![
+            Column::new("block_number".into(), transfers.keys().copied().collect::<Vec<Option<u32>>>()),
+            Column::new("transfers".into(), transfers.values().copied().collect::<Vec<u64>>()),
+        ])?)
+    }
+
+    /// Transfers of every token, most transferred first.
+    pub fn transfers_per_token(&self) -> Result<DataFrame> {
+        self.top_tokens(usize::MAX)
+    }
+
+    /// The `n` most transferred tokens with their transfer counts. Token runs are
+    /// summed and token ids counted; ties are ordered by token.
+    pub fn top_tokens(&self, n: usize) -> Result<DataFrame> {
+        let top = match self.codec("erc20")? {
+            "rle" => {
+                let mut tokens = RLECompressedErc20Series::new();
+                tokens.load(&self.streams)?;
+                self.streams.check_runs("token_counts", tokens.values.len(), &tokens.counts)?;
+                let mut transfers: HashMap<Option<&str>, u64> = HashMap::new();
+                for (token, count) in tokens.values.iter().zip(tokens.counts.iter()) {
+                    *transfers.entry(token.as_deref()).or_insert(0) += *count as u64;
+                }
+                top_counts(transfers.into_iter().collect(), n, |token| token.map(str::to_string))
+            },
+            "dictionary" => {
+                let mut tokens = DictionaryCompressedErc20Series::new();
+                tokens.load(&self.streams)?;
+                let transfers = count_ids(tokens.ids.iter().map(|id| Some(*id)), tokens.tokens.len(), "erc20")?;
+                top_counts(transfers, n, |id| id.and_then(|id| tokens.tokens[id as usize].clone()))
+            },
+            "vocabulary" | "registry" => {
+                let vocabulary = self.vocabulary()?;
+                let transfers = count_ids(vocabulary.ids[0].iter().copied(), vocabulary.vocabulary.len(), "erc20")?;
+                top_counts(transfers, n, |id| id.map(|id| format!("0x{}", vocabulary.vocabulary[id as usize])))
+            },
+            codec => return Err(BladeError::Codec(format!("no analytics for column `erc20` with codec `{}`", codec))),
+        };
+        Ok(DataFrame::new(vec![
+            Column::new("erc20".into(), top.iter().map(|(token, _)| token.clone()).collect::<Vec<Option<String>>>()),
+            Column::new("transfers".into(), top.iter().map(|(_, count)| *count).collect::<Vec<u64>>()),
+        ])?)
+    }
+
+    /// The `n` (from_address, to_address) pairs with the most transfers. Pair
+    /// dictionary indices or the two vocabulary ids of each row are counted; ties
+    /// are ordered by addresses.
+    pub fn top_address_pairs(&self, n: usize) -> Result<DataFrame> {
+        let top = match self.address_codec()? {
+            "raw" => {
+                let pairs = self.address_pairs()?;
+                let mut transfers: HashMap<&str, u64> = HashMap::new();
+                for pair in pairs.raw_pairs.iter() {
+                    *transfers.entry(pair.as_str()).or_insert(0) += 1;
+                }
+                top_counts(transfers.into_iter().collect(), n, split_pair)
+            },
+            "dictionary" => {
+                let pairs = self.address_pairs()?;
+                let transfers = count_ids(pairs.index.iter().map(|id| Some(*id)), pairs.address_pairs.len(), "address pair")?;
+                top_counts(transfers, n, |id| id.map(|id| split_pair(&pairs.address_pairs[id as usize])).unwrap_or_default())
+            },
+            _ => {
+                let vocabulary = self.vocabulary()?;
+                let mut transfers: HashMap<(Option<u32>, Option<u32>), u64> = HashMap::new();
+                for pair in vocabulary.ids[1].iter().copied().zip(vocabulary.ids[2].iter().copied()) {
+                    *transfers.entry(pair).or_insert(0) += 1;
+                }
+                let address = |id: Option<u32>| id.and_then(|id| vocabulary.vocabulary.get(id as usize)).map(|address| format!("0x{}", address));
+                top_counts(transfers.into_iter().collect(), n, |(from, to)| (address(from), address(to)))
+            },
+        };
+        Ok(DataFrame::new(vec![
+            Column::new("from_address".into(), top.iter().map(|((from, _), _)| from.clone()).collect::<Vec<Option<String>>>()),
+            Column::new("to_address".into(), top.iter().map(|((_, to), _)| to.clone()).collect::<Vec<Option<String>>>()),
+            Column::new("transfers".into(), top.iter().map(|(_, count)| *count).collect::<Vec<u64>>()),
+        ])?)
+    }
+
+    /// Distinct non-null senders of the transfers in blocks `start..=end`. Block
+    /// runs select the rows; sender ids or pair indices of those rows are
+    /// collected, and only distinct pairs are split into addresses.
+    pub fn unique_senders(&self, start: u32, end: u32) -> Result<u64> {
+        let rows = self.block_rows(start, end)?;
+        let senders = match self.address_codec()? {
+            "raw" => {
+                let pairs = self.address_pairs()?;
+                let senders: HashSet<&str> = rows.into_iter()
+                    .flat_map(|rows| pairs.raw_pairs[rows].iter())
+                    .filter_map(|pair| sender(pair))
+                    .collect();
+                senders.len()
+            },
+            "dictionary" => {
+                let pairs = self.address_pairs()?;
+                let ids: HashSet<u32> = rows.into_iter().flat_map(|rows| pairs.index[rows].iter().copied()).collect();
+                let senders: HashSet<&str> = ids.into_iter()
+                    .filter_map(|id| pairs.address_pairs.get(id as usize).and_then(|pair| sender(pair)))
+                    .collect();
+                senders.len()
+            },
+            _ => {
+                let vocabulary = self.vocabulary()?;
+                let senders: HashSet<u32> = rows.into_iter().flat_map(|rows| vocabulary.ids[1][rows].iter().flatten().copied()).collect();
+                senders.len()
+            },
+        };
+        Ok(senders as u64)
+    }
+
+    fn codec(&self, column: &str) -> Result<&str> {
+        self.manifest.column(column)
+            .map(|entry| entry.codec.as_str())
+            .ok_or_else(|| BladeError::Schema(format!("column `{}` is not stored in the archive", column)))
+    }
+
+    fn blocks(&self) -> Result<RLECompressedBlockNumberSeries> {
+        match self.codec("block_number")? {
+            "rle" => {
+                let mut blocks = RLECompressedBlockNumberSeries::new();
+                blocks.load(&self.streams)?;
+                self.streams.check_runs("block_counts", blocks.values.len(), &blocks.counts)?;
+                Ok(blocks)
+            },
+            codec => Err(BladeError::Codec(format!("no analytics for column `block_number` with codec `{}`", codec))),
+        }
+    }
+
+    /// Stored row ranges of the block runs within `start..=end`; the runs are
+    /// checked to cover the archive's rows, so per-row streams of that length
+    /// can be sliced by them.
+    fn block_rows(&self, start: u32, end: u32) -> Result<Vec<std::ops::Range<usize>>> {
+        let blocks = self.blocks()?;
+        let mut rows: Vec<std::ops::Range<usize>> = Vec::new();
+        let mut row = 0;
+        for (block, count) in blocks.values.iter().zip(blocks.counts.iter()) {
+            let next = row + *count as usize;
+            if block.is_some_and(|block| (start..=end).contains(&block)) {
+                match rows.last_mut() {
+                    Some(last) if last.end == row => last.end = next,
+                    _ => rows.push(row..next),
+                }
+            }
+            row = next;
+        }
+        Ok(rows)
+    }
+
+    /// Codec of the from_address / to_address columns, encoded together.
+    fn address_codec(&self) -> Result<&str> {
+        match (self.codec("from_address")?, self.codec("to_address")?) {
+            (from, to) if from != to => Err(BladeError::Codec(format!("from_address and to_address are encoded apart ({} / {})", from, to))),
+            (codec @ ("raw" | "dictionary" | "vocabulary" | "registry"), _) => Ok(codec),
+            (codec, _) => Err(BladeError::Codec(format!("no analytics for address columns with codec `{}`", codec))),
+        }
+    }
+
+    /// Address pairs, raw or dictionary, with one raw pair or index per row.
+    fn address_pairs(&self) -> Result<DictionaryCompressedAddressSeries> {
+        let mut pairs = DictionaryCompressedAddressSeries::new();
+        pairs.load(&self.streams)?;
+        match self.streams.contains("address_pairs") {
+            true => self.check_rows("address_pairs", pairs.raw_pairs.len())?,
+            false => self.check_rows("address_index", pairs.index.len())?,
+        }
+        Ok(pairs)
+    }
+
+    /// Address vocabulary, with one id per row in each id stream.
+    fn vocabulary(&self) -> Result<SharedVocabularyCompressedAddressSeries> {
+        let mut vocabulary = SharedVocabularyCompressedAddressSeries::new();
+        vocabulary.load(&self.streams)?;
+        for ((_, stream), ids) in VOCABULARY_COLUMNS.iter().zip(vocabulary.ids.iter()) {
+            self.check_rows(stream, ids.len())?;
+        }
+        Ok(vocabulary)
+    }
+
+    /// Error unless a per-row stream of `len` values holds one value per row of
+    /// the archive, as slicing it by block-run row ranges requires.
+    fn check_rows(&self, stream: &str, len: usize) -> Result<()> {
+        if len != self.streams.rows {
+            return Err(BladeError::Integrity(format!("stream `{}` holds {} values for {} rows", stream, len, self.streams.rows)));
+        }
+        Ok(())
+    }
+}
+
+/// Count the rows of each id of a dictionary of `size` entries, nulls apart.
+fn count_ids(ids: impl Iterator<Item = Option<u32>>, size: usize, name: &str) -> Result<Vec<(Option<u32>, u64)>> {
+    let mut counts: Vec<u64> = vec![0; size];
+    let mut nulls: u64 = 0;
+    for id in ids {
+        match id {
+            Some(id) => *counts.get_mut(id as usize)
+                .ok_or_else(|| BladeError::Integrity(format!("{} id {} is outside the dictionary of {} entries", name, id, size)))? += 1,
+            None => nulls += 1,
+        }
+    }
+    let mut counted: Vec<(Option<u32>, u64)> = counts.into_iter()
+        .enumerate()
+        .filter(|(_, count)| *count > 0)
+        .map(|(id, count)| (Some(id as u32), count))
+        .collect();
+    if nulls > 0 {
+        counted.push((None, nulls));
+    }
+    Ok(counted)
+}
+
+/// The `n` largest counts, most first and ties ordered by their resolved key.
+/// Only keys counted at least as often as the n-th largest are resolved.
+fn top_counts<K, R: Ord>(mut counts: Vec<(K, u64)>, n: usize, resolve: impl Fn(K) -> R) -> Vec<(R, u64)> {
+    if n == 0 || counts.is_empty() {
+        return Vec::new();
+    }
+    if n < counts.len() {
+        counts.select_nth_unstable_by(n - 1, |a, b| b.1.cmp(&a.1));
+        let threshold = counts[n - 1].1;
+        counts.retain(|(_, count)| *count >= threshold);
+    }
+    let mut top: Vec<(R, u64)> = counts.into_iter().map(|(key, count)| (resolve(key), count)).collect();
+    top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    top.truncate(n);
+    top
+}
+
+/// From and to address of a 40 + 40 hex character pair.
+fn split_pair(pair: &str) -> (Option<String>, Option<String>) {
+    match pair.get(..40).zip(pair.get(40..)) {
+        Some((from, to)) => (decode_address(from), decode_address(to)),
+        None => (None, None),
+    }
+}
+
+/// Sender half of a pair, unless it stands for a null address.
+fn sender(pair: &str) -> Option<&str> {
+    pair.get(..40).filter(|from| decode_address(from).is_some())
+}
@@ -0,0 +1,160 @@
+// Trained dictionary bundle for repeated compression jobs.
+// Like zstd's trained dictionaries, a bundle is built once from a sample of
+// transfers files and reused: its frequent addresses (tokens included) start the
+// address vocabulary, and its common amounts start each token's hot dictionary,
+// so archives only store what the bundle lacks. Small chunks get the dictionary
+// a large file would have built for itself.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use polars::prelude::*;
+use crate::error::{BladeError, Result};
+use super::ingestion::TransferIngestion;
+use super::manifest::BundleReference;
+use super::registry::{locate_beside_archive, REGISTRY_STREAM};
+use super::writer::atomic_write;
+use crate::transfers::compression::address_vocabulary::VOCABULARY_COLUMNS;
+use crate::transfers::compression::Streams;
+
+/// Streams holding the bundle's hot amounts, added by the reader.
+pub const BUNDLE_TOKENS_STREAM: &str = "bundle_amount_tokens";
+pub const BUNDLE_SIZES_STREAM: &str = "bundle_amount_sizes";
+pub const BUNDLE_AMOUNTS_STREAM: &str = "bundle_amounts";
+
+/// Occurrences across the sample before an address or amount enters the bundle.
+const TRAIN_MIN_COUNT: usize = 3;
+/// Most addresses / amounts kept, so that bundles stay quick to load.
+const TRAIN_MAX_ADDRESSES: usize = 1 << 16;
+const TRAIN_MAX_AMOUNTS: usize = 1 << 16;
+
+#[derive(Default)]
+pub struct DictionaryBundle {
+    pub path: PathBuf,                                   // bundle file
+    pub addresses: Vec<String>,                          // frequent addresses without '0x', most frequent first
+    pub amounts: Vec<(Option<String>, Vec<String>)>,     // common amounts of each token, most frequent first
+}
+
+impl DictionaryBundle {
+
+    /// Train a bundle on a sample of transfers files: addresses of the erc20,
+    /// from and to columns, and amounts within each token, seen at least
+    /// `TRAIN_MIN_COUNT` times.
+    pub fn train(path: &Path, filepaths: &[PathBuf]) -> Result<Self> {
+        let mut address_counts: HashMap<String, usize> = HashMap::new();
+        let mut amount_counts: HashMap<(Option<String>, String), usize> = HashMap::new();
+        for filepath in filepaths {
+            let mut transfer: TransferIngestion = TransferIngestion::new();
+            let dataset = transfer.check_schema_validity(filepath)?;
+
+            // values that are not 0x-prefixed addresses never enter the vocabulary
+            for (name, _) in VOCABULARY_COLUMNS.iter() {
+                for value in dataset.column(name)?.str()?.into_iter().flatten() {
+                    if let Some(address) = value.strip_prefix("0x").filter(|hex| hex.len() == 40 && hex.bytes().all(|b| b.is_ascii_hexdigit())) {
+                        *address_counts.entry(address.to_string()).or_insert(0) += 1;
+                    }
+                }
+            }
+            let tokens = dataset.column("erc20")?.str()?;
+            let value_strings = dataset.column("value_string")?.str()?;
+            for (token, value) in tokens.iter().zip(value_strings.iter()) {
+                if let Some(value) = value {
+                    *amount_counts.entry((token.map(str::to_string), value.to_string())).or_insert(0) += 1;
+                }
+            }
+        }
+
+        // Most frequent first, ties broken by value so that training is deterministic
+        let mut addresses: Vec<(String, usize)> = address_counts.into_iter().filter(|(_, count)| *count >= TRAIN_MIN_COUNT).collect();
+        addresses.sort_unstable_by(|(address_a, count_a), (address_b, count_b)| count_b.cmp(count_a).then(address_a.cmp(address_b)));
+        addresses.truncate(TRAIN_MAX_ADDRESSES);
+
+        let mut amounts: Vec<((Option<String>, String), usize)> = amount_counts.into_iter().filter(|(_, count)| *count >= TRAIN_MIN_COUNT).collect();
+        amounts.sort_unstable_by(|(key_a, count_a), (key_b, count_b)| count_b.cmp(count_a).then(key_a.cmp(key_b)));
+        amounts.truncate(TRAIN_MAX_AMOUNTS);
+        let mut positions: HashMap<Option<String>, usize> = HashMap::new();
+        let mut dictionaries: Vec<(Option<String>, Vec<String>)> = Vec::new();
+        for ((token, amount), _) in amounts {
+            let position = *positions.entry(token.clone()).or_insert_with(|| {
+                dictionaries.push((token, Vec::new()));
+                dictionaries.len() - 1
+            });
+            dictionaries[position].1.push(amount);
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            addresses: addresses.into_iter().map(|(address, _)| address).collect(),
+            amounts: dictionaries,
+        })
+    }
+
+    /// Open the bundle at `path`.
+    pub fn open(path: &Path) -> Result<Self> {
+        let df = ParquetReader::new(File::open(path)?).finish()?;
+        let addresses = df.column("address")?.str()?.into_iter().flatten().map(str::to_string).collect();
+
+        // amount rows are grouped by token; padding rows have no amount
+        let mut amounts: Vec<(Option<String>, Vec<String>)> = Vec::new();
+        for (token, amount) in df.column("amount_token")?.str()?.iter().zip(df.column("amount")?.str()?.iter()) {
+            let Some(amount) = amount else { continue };
+            match amounts.last_mut() {
+                Some((last, token_amounts)) if last.as_deref() == token => token_amounts.push(amount.to_string()),
+                _ => amounts.push((token.map(str::to_string), vec![amount.to_string()])),
+            }
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            addresses,
+            amounts,
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let tokens: Vec<Option<&str>> = self.amounts.iter()
+            .flat_map(|(token, amounts)| std::iter::repeat_n(token.as_deref(), amounts.len()))
+            .collect();
+        let amounts: Vec<&str> = self.amounts.iter().flat_map(|(_, amounts)| amounts.iter().map(String::as_str)).collect();
+        let dataframes = [
+            DataFrame::new(vec![Column::new("address".into(), &self.addresses)])?,
+            DataFrame::new(vec![Column::new("amount_token".into(), tokens), Column::new("amount".into(), amounts)])?,
+        ];
+        let mut df = polars::functions::concat_df_horizontal(&dataframes, true)?;
+        atomic_write(&self.path, |file| {
+            ParquetWriter::new(file).finish(&mut df)?;
+            Ok(())
+        })
+    }
+
+    /// Reference to this bundle, recorded in the manifest of archives using it.
+    pub fn reference(&self) -> Result<BundleReference> {
+        Ok(BundleReference {
+            path: self.path.to_string_lossy().to_string(),
+            checksum: crc32fast::hash(&fs::read(&self.path)?),
+        })
+    }
+
+    /// Add the bundle to the streams of an archive written with it: the
+    /// addresses as the `address_registry` stream the vocabulary starts from,
+    /// and the hot amounts as `bundle_amount_tokens / sizes / amounts`.
+    pub fn insert_streams(&self, streams: &mut Streams) {
+        let tokens: Vec<Option<&str>> = self.amounts.iter().map(|(token, _)| token.as_deref()).collect();
+        let sizes: Vec<u32> = self.amounts.iter().map(|(_, amounts)| amounts.len() as u32).collect();
+        let amounts: Vec<&str> = self.amounts.iter().flat_map(|(_, amounts)| amounts.iter().map(String::as_str)).collect();
+        streams.insert(Column::new(REGISTRY_STREAM.into(), &self.addresses));
+        streams.insert(Column::new(BUNDLE_TOKENS_STREAM.into(), tokens));
+        streams.insert(Column::new(BUNDLE_SIZES_STREAM.into(), sizes));
+        streams.insert(Column::new(BUNDLE_AMOUNTS_STREAM.into(), amounts));
+    }
+}
+
+/// Open the bundle an archive was written with, looked up at its recorded path
+/// then beside the archive, and check it is the same bundle.
+pub fn open_referenced(reference: &BundleReference, archive_filepath: Option<&Path>) -> Result<DictionaryBundle> {
+    let path = locate_beside_archive(&reference.path, archive_filepath, "dictionary bundle")?;
+    let checksum = crc32fast::hash(&fs::read(&path)?);
+    if checksum != reference.checksum {
+        return Err(BladeError::Integrity(format!("dictionary bundle {:?} is not the one the archive was written with: expected {:08x}, found {:08x}", path, reference.checksum, checksum)));
+    }
+    DictionaryBundle::open(&path)
+}
@@ -0,0 +1,308 @@
+// Decoding a BLADE archive one range of rows at a time.
+// Codecs decode whole streams, so `ArchiveChunks` slices the encoded streams of
+// the archive into the streams of one range of stored rows and runs the codecs
+// on those: runs are trimmed at the range edges, per row streams and packed ids
+// sliced, and streams consumed in row order (value literals, wide mantissas,
+// keyed hashes) followed through cursors. Dictionaries serve every range. The
+// ranges of a reordered archive end where the rows stored so far are exactly
+// cryo's rows so far, so each range is restored to cryo's order on its own.
+
+use std::collections::{HashMap, HashSet};
+use polars::prelude::*;
+use crate::error::{BladeError, Result};
+use super::compression::Streams;
+use super::compression::bit_pack::{bit_width, pack, unpack_range};
+use super::compression::transaction_hash::{transaction_keys, RLECompressedTransactionHashSeries, TransactionKey};
+use super::manifest::Manifest;
+use super::row_order::RowOrder;
+use super::transfers::Transfer;
+
+/// Stored rows decoded at a time.
+pub const CHUNK_ROWS: usize = 65_536;
+
+/// Value / count streams of the run-length encoded columns.
+const RUN_STREAMS: [(&str, &str); 5] = [
+    ("block_values", "block_counts"),
+    ("trans_index_values", "trans_index_counts"),
+    ("log_index_values", "log_index_counts"),
+    ("token_values", "token_counts"),
+    ("chain_id_values", "chain_id_counts"),
+];
+
+/// Streams holding one value per stored row.
+const ROW_STREAMS: [&str; 6] = ["address_pairs", "address_index", "erc20_ids", "from_address_ids", "to_address_ids", "value_hot_index"];
+
+/// Bit-packed id streams, with the dictionary their width is derived from.
+const PACKED_STREAMS: [(&str, &str); 2] = [
+    ("tx_hash_ids_packed", "tx_hash_dictionary"),
+    ("token_ids_packed", "token_dictionary"),
+];
+
+/// Position of a walk through runs: the next run and its first stored row.
+#[derive(Debug, Clone, Copy, Default)]
+struct RunCursor {
+    run: usize,
+    first: usize,
+}
+
+/// Iterator decoding the columns of an archive one range of rows at a time,
+/// in cryo's order.
+pub struct ArchiveChunks {
+    transfer: Transfer,                                     // decodes each range against the archive manifest
+    streams: Streams,                                       // encoded streams of the whole archive
+    columns: Vec<String>,                                   // columns decoded
+    ends: Vec<usize>,                                       // stored row ending each range
+    next: usize,                                            // next range to decode
+    runs: HashMap<&'static str, (Vec<u32>, RunCursor)>,     // run lengths of each count stream
+    hash_runs: Option<(Vec<u32>, RunCursor)>,               // run lengths of the transaction hash runs
+    packed: HashMap<&'static str, (Vec<u32>, u8)>,          // words and bit width of each packed id stream
+    row_order: Option<(RowOrder, RunCursor)>,               // stored order of a reordered archive
+    literal: usize,                                         // next mantissa / exponent literal
+    wide: usize,                                            // next wide mantissa
+    keys: HashMap<TransactionKey, u32>,                     // keyed hash position of every transaction met so far
+}
+
+impl ArchiveChunks {
+
+    /// Decode `columns` of an archive read into `streams`, up to `chunk_rows`
+    /// stored rows at a time. Runs and the row order are checked against the
+    /// archive's rows up front.
+    pub fn new(manifest: Manifest, streams: Streams, columns: &[String], chunk_rows: usize) -> Result<Self> {
+        for name in columns {
+            if manifest.column(name).is_none() {
+                return Err(BladeError::Schema(format!("column `{}` is not stored in the archive", name)));
+            }
+        }
+
+        let mut runs = HashMap::new();
+        for (values, counts) in RUN_STREAMS {
+            if streams.contains(counts) {
+                let lengths: Vec<u32> = streams.get(counts)?.u32()?.into_no_null_iter().collect();
+                streams.check_runs(counts, streams.get(values)?.len(), &lengths)?;
+                runs.insert(counts, (lengths, RunCursor::default()));
+            }
+        }
+
+        // run starts of the transaction hashes, as lengths
+        let mut hash_runs = None;
+        if streams.contains("tx_hash_index") {
+            let mut hash_compression = RLECompressedTransactionHashSeries::new();
+            hash_compression.load(&streams)?;
+            hash_compression.check_run_starts()?;
+            let ends = hash_compression.index.iter().skip(1).copied().chain(std::iter::once(streams.rows as u32));
+            let lengths: Vec<u32> = hash_compression.index.iter().zip(ends).map(|(start, end)| end - start).collect();
+            hash_runs = Some((lengths, RunCursor::default()));
+        }
+
+        let mut packed = HashMap::new();
+        for (ids, dictionary) in PACKED_STREAMS {
+            if streams.contains(ids) {
+                let words: Vec<u32> = streams.get(ids)?.u32()?.into_no_null_iter().collect();
+                let width = bit_width(streams.get(dictionary)?.len().saturating_sub(1) as u32);
+                packed.insert(ids, (words, width));
+            }
+        }
+
+        let row_order = RowOrder::load(&streams)?;
+        if let Some(order) = &row_order {
+            let covered: u64 = order.lengths.iter().map(|length| *length as u64).sum();
+            if covered != streams.rows as u64 {
+                return Err(BladeError::Integrity(format!("row order covers {} rows, expected {}", covered, streams.rows)));
+            }
+        }
+
+        let mut transfer = Transfer::new();
+        transfer.manifest = manifest;
+        Ok(Self {
+            transfer,
+            ends: chunk_ends(streams.rows, row_order.as_ref(), chunk_rows.max(1)),
+            streams,
+            columns: columns.to_vec(),
+            next: 0,
+            runs,
+            hash_runs,
+            packed,
+            row_order: row_order.map(|order| (order, RunCursor::default())),
+            literal: 0,
+            wide: 0,
+            keys: HashMap::new(),
+        })
+    }
+
+    /// Row count of the archive.
+    pub fn rows(&self) -> usize {
+        self.streams.rows
+    }
+
+    /// Decode stored rows `start..end`.
+    fn decode(&mut self, start: usize, end: usize) -> Result<DataFrame> {
+        let rows = end - start;
+
+        // dictionaries and vocabularies are shared by every range; the streams
+        // indexed by row are replaced by their part below
+        let mut chunk = Streams::new(rows);
+        for column in self.streams.columns.values() {
+            chunk.insert(column.clone());
+        }
+
+        for (values, counts) in RUN_STREAMS {
+            if let Some((lengths, cursor)) = self.runs.get_mut(counts) {
+                let overlaps = overlapping(lengths, cursor, start, end);
+                chunk.insert(take_runs(self.streams.get(values)?, &overlaps)?);
+                chunk.insert(Column::new(counts.into(), overlaps.iter().map(|(_, _, length)| *length as u32).collect::<Vec<u32>>()));
+            }
+        }
+
+        if let Some((lengths, cursor)) = &mut self.hash_runs {
+            let overlaps = overlapping(lengths, cursor, start, end);
+            let mut first = 0u32;
+            let starts: Vec<u32> = overlaps.iter()
+                .map(|(_, _, length)| {
+                    first += *length as u32;
+                    first - *length as u32
+                })
+                .collect();
+            chunk.insert(Column::new("tx_hash_index".into(), starts));
+            chunk.insert(take_runs(self.streams.get("tx_hash_values")?, &overlaps)?);
+        }
+
+        for stream in ROW_STREAMS {
+            if let Ok(column) = self.streams.get(stream) {
+                chunk.insert(column.slice(start as i64, rows));
+            }
+        }
+
+        for (stream, (words, width)) in self.packed.iter() {
+            let ids = unpack_range(words, *width, start, rows)?;
+            chunk.insert(Column::new((*stream).into(), pack(&ids, *width)));
+        }
+
+        // literals are one per row, or one per row without a hot amount
+        if self.streams.contains("value_exponents") {
+            let literals = match self.streams.get("value_hot_index") {
+                Ok(index) => index.slice(start as i64, rows).null_count(),
+                Err(_) => rows,
+            };
+            let exponents = self.streams.get("value_exponents")?.slice(self.literal as i64, literals);
+            let mantissas = self.streams.get("value_mantissas")?.slice(self.literal as i64, literals);
+            let wide = exponents.u32()?.iter().zip(mantissas.u64()?.iter())
+                .filter(|(exponent, mantissa)| exponent.is_some() && mantissa.is_none())
+                .count();
+            chunk.insert(self.streams.get("value_wide_mantissas")?.slice(self.wide as i64, wide));
+            chunk.insert(exponents);
+            chunk.insert(mantissas);
+            self.literal += literals;
+            self.wide += wide;
+        }
+
+        // keyed hashes take positions in first seen order over the whole archive;
+        // the range stores those of its transactions in its own first seen order
+        if let Ok(hashes) = self.streams.get("tx_hash_keyed_values") {
+            let mut seen: HashSet<TransactionKey> = HashSet::new();
+            let mut positions: Vec<IdxSize> = Vec::new();
+            for key in transaction_keys(&chunk)? {
+                let next = self.keys.len() as u32;
+                let position = *self.keys.entry(key).or_insert(next);
+                if seen.insert(key) {
+                    if position as usize >= hashes.len() {
+                        return Err(BladeError::Integrity(format!("transaction {:?} has no hash among {} keyed hashes", key, hashes.len())));
+                    }
+                    positions.push(position as IdxSize);
+                }
+            }
+            chunk.insert(hashes.take(&IdxCa::from_vec("".into(), positions))?);
+        }
+
+        if let Some((order, cursor)) = &mut self.row_order {
+            let mut chunk_order = RowOrder::new();
+            for (run, offset, length) in overlapping(&order.lengths, cursor, start, end) {
+                let original = order.starts[run] as usize + offset;
+                let relative = original.checked_sub(start)
+                    .ok_or_else(|| BladeError::Integrity(format!("row order moves original row {} past stored row {}", original, start)))?;
+                chunk_order.starts.push(relative as u32);
+                chunk_order.lengths.push(length as u32);
+            }
+            for column in chunk_order.create_compressed_df()?.get_columns() {
+                chunk.insert(column.clone());
+            }
+        }
+
+        self.transfer.decompress_columns(&chunk, &self.columns)
+    }
+}
+
+impl Iterator for ArchiveChunks {
+    type Item = Result<DataFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let end = *self.ends.get(self.next)?;
+        let start = self.next.checked_sub(1).map_or(0, |previous| self.ends[previous]);
+        self.next += 1;
+        let decoded = self.decode(start, end);
+        if decoded.is_err() {
+            // cursors are left mid range; later ranges cannot be decoded
+            self.next = self.ends.len();
+        }
+        Some(decoded)
+    }
+}
+
+/// Stored row ending each range of at most about `chunk_rows` rows. A range of
+/// a reordered archive ends only where the rows stored before the end hold
+/// exactly cryo's rows before it; a run kept in cryo's order is cut anywhere.
+fn chunk_ends(rows: usize, row_order: Option<&RowOrder>, chunk_rows: usize) -> Vec<usize> {
+    let mut ends: Vec<usize> = Vec::new();
+    match row_order {
+        None => ends.extend((1..).map(|i| i * chunk_rows).take_while(|end| *end < rows)),
+        Some(order) => {
+            // `seen`: one past the furthest original row stored so far
+            let (mut stored, mut seen) = (0usize, 0usize);
+            for (start, length) in order.starts.iter().zip(order.lengths.iter()) {
+                let (start, length) = (*start as usize, *length as usize);
+                if start == stored && seen == stored {
+                    let mut cut = ends.last().copied().unwrap_or(0) + chunk_rows;
+                    while cut < stored + length {
+                        ends.push(cut);
+                        cut += chunk_rows;
+                    }
+                }
+                stored += length;
+                seen = seen.max(start + length);
+                if seen == stored && stored < rows && stored >= ends.last().copied().unwrap_or(0) + chunk_rows {
+                    ends.push(stored);
+                }
+            }
+        },
+    }
+    if rows > 0 {
+        ends.push(rows);
+    }
+    ends
+}
+
+/// Runs of `lengths` overlapping stored rows `start..end`, walking on from
+/// `cursor`: each run, the offset of the overlap in it and its length.
+fn overlapping(lengths: &[u32], cursor: &mut RunCursor, start: usize, end: usize) -> Vec<(usize, usize, usize)> {
+    while cursor.run < lengths.len() && cursor.first + lengths[cursor.run] as usize <= start {
+        cursor.first += lengths[cursor.run] as usize;
+        cursor.run += 1;
+    }
+    let mut overlaps = Vec::new();
+    let (mut run, mut first) = (cursor.run, cursor.first);
+    while run < lengths.len() && first < end {
+        let length = lengths[run] as usize;
+        let (from, to) = (start.max(first), end.min(first + length));
+        if to > from {
+            overlaps.push((run, from - first, to - from));
+        }
+        first += length;
+        run += 1;
+    }
+    overlaps
+}
+
+/// Values of the overlapping runs.
+fn take_runs(values: &Column, overlaps: &[(usize, usize, usize)]) -> Result<Column> {
+    let runs: Vec<IdxSize> = overlaps.iter().map(|(run, _, _)| *run as IdxSize).collect();
+    Ok(values.take(&IdxCa::from_vec("".into(), runs))?)
+}
@@ -0,0 +1,162 @@
+// Merge and compaction of BLADE archives covering adjacent block ranges.
+// Archives are decoded, checked for contiguous ranges and duplicate rows, then
+// compressed again as one dataset so dictionaries are shared and codec
+// selection is re-run on the combined data.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::error::{BladeError, Result};
+use polars::prelude::*;
+
+use super::ingestion::parse_cryo_filename;
+use super::manifest::Manifest;
+use super::options::CompressOptions;
+use super::output::OutputOptions;
+use super::reader::{is_blade_archive, parquet_projection_reader};
+use super::transfers::Transfer;
+
+/// An archive on disk with its block range and manifest.
+type ArchiveEntry = (PathBuf, (u32, u32), Manifest);
+
+/// Merge archives of adjacent block ranges into a single archive at `output_filepath`.
+pub fn merge_archives(archive_filepaths: &[PathBuf], output_filepath: &Path, options: &CompressOptions) -> Result<Manifest> {
+    if archive_filepaths.is_empty() {
+        return Err(BladeError::Format("no archives to merge".to_string()));
+    }
+
+    // Decode every archive, ordered by the first block it covers
+    let mut archives: Vec<((u32, u32), Manifest, DataFrame)> = Vec::new();
+    for filepath in archive_filepaths {
+        let mut transfer = Transfer::new();
+        let df = transfer.decompress(filepath)?;
+        let Some(range) = block_range(&transfer.manifest) else {
+            return Err(BladeError::Format(format!("archive {:?} has no recorded block range", filepath)));
+        };
+        archives.push((range, transfer.manifest, df));
+    }
+    archives.sort_by_key(|((start_block, _), _, _)| *start_block);
+
+    // Block ranges must follow each other without gaps or overlaps
+    for pair in archives.windows(2) {
+        let ((_, previous_end), (next_start, _)) = (pair[0].0, pair[1].0);
+        if next_start <= previous_end {
+            return Err(BladeError::Integrity(format!("block ranges overlap: archive ending at {} and archive starting at {}", previous_end, next_start)));
+        }
+        if next_start != previous_end + 1 {
+            return Err(BladeError::Integrity(format!("block ranges are not contiguous: gap between blocks {} and {}", previous_end, next_start)));
+        }
+    }
+
+    // All archives must carry the same columns to be stacked
+    let columns = archives[0].1.column_names();
+    let mut merged = archives[0].2.clone();
+    for (_, manifest, df) in archives.iter().skip(1) {
+        if manifest.column_names() != columns {
+            return Err(BladeError::Schema(format!("archives store different columns: {:?} vs {:?}", columns, manifest.column_names())));
+        }
+        merged.vstack_mut(df)?;
+    }
+    merged.align_chunks();
+    check_duplicate_rows(&merged)?;
+
+    // Compress as one dataset; dictionaries and codec choices are rebuilt
+    let mut transfer = Transfer::new();
+    transfer.options = options.clone();
+    transfer.compress_dataframe(&merged)?;
+    transfer.manifest.start_block = archives.first().map(|((start_block, _), _, _)| *start_block);
+    transfer.manifest.end_block = archives.last().map(|((_, end_block), _, _)| *end_block);
+    transfer.output_filepath = output_filepath.to_path_buf();
+    transfer.write_archive()?;
+    Ok(transfer.manifest)
+}
+
+/// Compact a directory of BLADE archives: runs of adjacent block ranges are
+/// merged into archives of up to `max_rows` rows, and the merged inputs removed.
+/// Merged archives are placed and named by the output options as the cryo chunk
+/// of their block range would be, and a run whose archive exists already fails,
+/// is skipped or replaced by the overwrite policy.
+/// Returns the paths of the newly written archives.
+pub fn compact_directory(directory: &Path, max_rows: usize, options: &CompressOptions, output: &OutputOptions) -> Result<Vec<PathBuf>> {
+    // Collect archive manifests in the directory, ordered by block range
+    let mut archives: Vec<ArchiveEntry> = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let filepath = entry?.path();
+        if !filepath.is_file() || !is_blade_archive(&filepath) {
+            continue;
+        }
+        // an empty projection reads the manifest only
+        let (manifest, _) = parquet_projection_reader(&filepath, &[])?;
+        if let Some(range) = block_range(&manifest) {
+            archives.push((filepath, range, manifest));
+        }
+    }
+    archives.sort_by_key(|(_, (start_block, _), _)| *start_block);
+
+    // Group adjacent archives until the row budget is reached
+    let mut groups: Vec<Vec<ArchiveEntry>> = Vec::new();
+    for archive in archives {
+        match groups.last_mut() {
+            Some(group) if extends_group(group, &archive, max_rows) => group.push(archive),
+            _ => groups.push(vec![archive]),
+        }
+    }
+
+    // Merge every group of two or more archives and remove the merged inputs
+    let mut written: Vec<PathBuf> = Vec::new();
+    for group in groups.into_iter().filter(|group| group.len() > 1) {
+        let ((start_block, _), (_, end_block)) = (group[0].1, group[group.len() - 1].1);
+        let chunk = match parse_cryo_filename(&group[0].0) {
+            Some(cryo) => format!("{}__{}__{}_to_{}.parquet", cryo.chain, cryo.dataset, start_block, end_block),
+            None => format!("{}_to_{}.parquet", start_block, end_block),
+        };
+        let Some(output_filepath) = output.archive_path(&directory.join(chunk), None, Some(start_block), Some(end_block))? else {
+            continue;
+        };
+        if let Some(parent) = output_filepath.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let inputs: Vec<PathBuf> = group.into_iter().map(|(filepath, _, _)| filepath).collect();
+
+        merge_archives(&inputs, &output_filepath, options)?;
+        for filepath in inputs.iter().filter(|filepath| **filepath != output_filepath) {
+            fs::remove_file(filepath)?;
+        }
+        written.push(output_filepath);
+    }
+    Ok(written)
+}
+
+/// First and last block of an archive, when both are recorded.
+fn block_range(manifest: &Manifest) -> Option<(u32, u32)> {
+    Some((manifest.start_block?, manifest.end_block?))
+}
+
+/// An archive extends a group when it starts right after the group's last
+/// archive and the group stays within `max_rows`.
+fn extends_group(group: &[ArchiveEntry], archive: &ArchiveEntry, max_rows: usize) -> bool {
+    let Some((_, (_, last_end), _)) = group.last() else {
+        return false;
+    };
+    let rows: usize = group.iter().map(|(_, _, manifest)| manifest.rows).sum();
+    let (_, (start_block, _), manifest) = archive;
+    *start_block == last_end + 1 && rows + manifest.rows <= max_rows
+}
+
+/// Each transfer is identified by its block and log index; error if any repeats.
+/// Rows missing either value cannot be identified and are not checked.
+fn check_duplicate_rows(dataset: &DataFrame) -> Result<()> {
+    let blocks = dataset.column("block_number")?.u32()?;
+    let logs = dataset.column("log_index")?.u32()?;
+
+    let mut seen: HashSet<(u32, u32)> = HashSet::new();
+    for (block, log) in blocks.iter().zip(logs.iter()) {
+        let (Some(block), Some(log)) = (block, log) else {
+            continue;
+        };
+        if !seen.insert((block, log)) {
+            return Err(BladeError::Integrity(format!("duplicate transfer at block {}, log index {}", block, log)));
+        }
+    }
+    Ok(())
+}
@@ -1,15 +1,40 @@
-use hex::{decode, encode};
-use std::mem;
-use anyhow::Result;
+use crate::error::{BladeError, Result};
 use polars::prelude::*;
 use std::collections::HashMap;
-use owo_colors::OwoColorize;
-use std::collections::HashSet;
+use rayon::prelude::*;
+use super::streams::Streams;
+use super::hex_string::strip_hex_prefix;
+
+/// Rows per chunk when encoding pairs and building the dictionary in parallel.
+pub const PARALLEL_CHUNK_ROWS: usize = 65_536;
+
+/// Stand-in for a null address within a pair; never valid hex, same width as an address.
+const NULL_ADDRESS: &str = "----------------------------------------";
+
+/// Encode a `from` / `to` address pair as 40 + 40 hex characters without the '0x' prefix.
+fn encode_pair(from: Option<&str>, to: Option<&str>, row: usize) -> Result<String> {
+    let from = match from {
+        Some(address) => strip_hex_prefix(address, "from_address", row, Some(40))?,
+        None => NULL_ADDRESS,
+    };
+    let to = match to {
+        Some(address) => strip_hex_prefix(address, "to_address", row, Some(40))?,
+        None => NULL_ADDRESS,
+    };
+    Ok(format!("{}{}", from, to))
+}
+
+/// Restore the '0x' prefix of one half of a pair, or the null it stands for.
+pub fn decode_address(half: &str) -> Option<String> {
+    (half != NULL_ADDRESS).then(|| format!("0x{}", half))
+}
 
 
+#[derive(Default)]
 pub struct DictionaryCompressedAddressSeries {
     pub index: Vec<u32>,
     pub address_pairs: Vec<String>,
+    pub raw_pairs: Vec<String>,          // every pair, when stored without a dictionary
 }
 
 pub enum CompressResult {
@@ -24,15 +49,16 @@ impl DictionaryCompressedAddressSeries {
         Self {
             index: Vec::new(),
             address_pairs: Vec::new(),
+            raw_pairs: Vec::new(),
         }
     }
 
     pub fn compress(&mut self, dataset: &DataFrame) -> Result<CompressResult> {
-        let from_addresses: &Column = dataset.column("from_address").unwrap();
-        let to_addresses: &Column = dataset.column("to_address").unwrap();
+        let from_addresses: &Column = dataset.column("from_address")?;
+        let to_addresses: &Column = dataset.column("to_address")?;
 
-        let from_address_series = from_addresses.str().unwrap();
-        let to_address_series = to_addresses.str().unwrap();
+        let from_address_series = from_addresses.str()?;
+        let to_address_series = to_addresses.str()?;
 
         // Check uniqueness ratio
         let unique_from = from_addresses.n_unique()?;
@@ -42,37 +68,65 @@ impl DictionaryCompressedAddressSeries {
         let from_ratio = unique_from as f64 / total_rows as f64;
         let to_ratio = unique_to as f64 / total_rows as f64;
 
-        if from_ratio > 0.3 || to_ratio > 0.3 {
-            let combined: Vec<String> = from_address_series.iter()
-                .zip(to_address_series.iter())
-                .map(|(from, to)| format!("{}{}", &from.unwrap()[2..], &to.unwrap()[2..]))
-                .collect();
+        // When appending, stay in the mode of the loaded archive
+        let appending = !self.raw_pairs.is_empty() || !self.index.is_empty();
+        let raw = if appending { !self.raw_pairs.is_empty() } else { from_ratio > 0.3 || to_ratio > 0.3 };
+
+        // Encode every pair; large columns are encoded in parallel chunks
+        let from_values: Vec<Option<&str>> = from_address_series.iter().collect();
+        let to_values: Vec<Option<&str>> = to_address_series.iter().collect();
+        let combined: Vec<String> = from_values.par_chunks(PARALLEL_CHUNK_ROWS)
+            .zip(to_values.par_chunks(PARALLEL_CHUNK_ROWS))
+            .enumerate()
+            .map(|(chunk, (froms, tos))| {
+                froms.iter().zip(tos.iter()).enumerate()
+                    .map(|(i, (from, to))| encode_pair(*from, *to, chunk * PARALLEL_CHUNK_ROWS + i))
+                    .collect::<Result<Vec<String>>>()
+            })
+            .collect::<Result<Vec<Vec<String>>>>()?
+            .concat();
+
+        if raw {
+            self.raw_pairs.extend(combined);
             
             let df = df! {
-                "address_pairs" => combined
+                "address_pairs" => &self.raw_pairs
             }?;
             return Ok(CompressResult::Original(df));
         }
 
-        // Compression logic
-        // let from_address_series = from_addresses.str().unwrap();
-        // let to_address_series = to_addresses.str().unwrap();
-        let mut address_to_index: HashMap<String, u32> = HashMap::new();
-        let mut unique_pairs: Vec<String> = Vec::new();
-        let mut all_indices: Vec<u32> = Vec::new();
-
-        for (from, to) in from_address_series.iter().zip(to_address_series.iter()) {
-            let combined = format!("{}{}", &from.unwrap()[2..], &to.unwrap()[2..]);
-            
-            let dict_index = if let Some(&idx) = address_to_index.get(&combined) {
-                idx
-            } else {
-                let new_idx = unique_pairs.len() as u32;
-                address_to_index.insert(combined.clone(), new_idx);
-                unique_pairs.push(combined);
-                new_idx
-            };
-            all_indices.push(dict_index);
+        // Compression logic; extends the loaded dictionary when appending
+        let mut address_to_index: HashMap<String, u32> = self.address_pairs.iter()
+            .enumerate()
+            .map(|(i, pair)| (pair.clone(), i as u32))
+            .collect();
+        let mut unique_pairs: Vec<String> = std::mem::take(&mut self.address_pairs);
+        let mut all_indices: Vec<u32> = std::mem::take(&mut self.index);
+
+        // Each chunk builds a local dictionary in parallel, in first occurrence order
+        let chunk_dictionaries: Vec<(Vec<&String>, Vec<u32>)> = combined.par_chunks(PARALLEL_CHUNK_ROWS)
+            .map(|chunk| {
+                let mut local_to_index: HashMap<&String, u32> = HashMap::new();
+                let mut local_pairs: Vec<&String> = Vec::new();
+                let local_indices: Vec<u32> = chunk.iter().map(|pair| {
+                    *local_to_index.entry(pair).or_insert_with(|| {
+                        local_pairs.push(pair);
+                        local_pairs.len() as u32 - 1
+                    })
+                }).collect();
+                (local_pairs, local_indices)
+            })
+            .collect();
+
+        // Merge local dictionaries in chunk order, so ids match a sequential pass
+        for (local_pairs, local_indices) in chunk_dictionaries {
+            let remap: Vec<u32> = local_pairs.into_iter().map(|pair| {
+                *address_to_index.entry(pair.clone()).or_insert_with(|| {
+                    unique_pairs.push(pair.clone());
+                    unique_pairs.len() as u32 - 1
+                })
+            }).collect();
+            all_indices.extend(local_indices.into_iter().map(|i| remap[i as usize]));
         }
 
         self.index = all_indices.clone();
@@ -101,11 +155,48 @@ impl DictionaryCompressedAddressSeries {
         }
     }
 
-    // pub fn decompress(&mut self) -> Result<()> {
-    //     // decode 
-    //     let reconstructed = format!("0x{}", hex::encode(&hash_bytes));
-    //     Ok(())
-    // }
+    /// Load raw pairs, or the dictionary and index, from the archive streams so
+    /// that a later `compress` call extends them.
+    pub fn load(&mut self, streams: &Streams) -> Result<()> {
+        if streams.contains("address_pairs") {
+            self.raw_pairs = streams.get("address_pairs")?.str()?.into_no_null_iter().map(|s| s.to_string()).collect();
+        } else {
+            self.index = streams.get("address_index")?.u32()?.into_no_null_iter().collect();
+            self.address_pairs = streams.get("address_values")?.str()?.into_no_null_iter().map(|s| s.to_string()).collect();
+        }
+        Ok(())
+    }
+
+    /// Decompression of address pairs back into `from_address` and `to_address` columns.
+    pub fn decompress(&mut self, streams: &Streams) -> Result<(Column, Column)> {
+        self.load(streams)?;
+
+        // raw pairs are stored one per row; dictionary pairs are resolved through the index
+        let pairs: Vec<String> = if streams.contains("address_pairs") {
+            self.raw_pairs.clone()
+        } else {
+            self.index.iter()
+                .map(|i| self.address_pairs.get(*i as usize).cloned()
+                    .ok_or_else(|| BladeError::Integrity(format!("address index {} is outside the dictionary of {} pairs", i, self.address_pairs.len()))))
+                .collect::<Result<_>>()?
+        };
+
+        // split each 40 + 40 hex character pair and restore the '0x' prefix
+        let mut from_addresses: Vec<Option<String>> = Vec::with_capacity(pairs.len());
+        let mut to_addresses: Vec<Option<String>> = Vec::with_capacity(pairs.len());
+        for pair in pairs.iter() {
+            if pair.len() != 80 || !pair.is_ascii() {
+                return Err(BladeError::Integrity(format!("address pair `{}` is not 40 + 40 characters", pair)));
+            }
+            let (from, to) = pair.split_at(40);
+            from_addresses.push(decode_address(from));
+            to_addresses.push(decode_address(to));
+        }
+        Ok((
+            Column::new("from_address".into(), from_addresses),
+            Column::new("to_address".into(), to_addresses),
+        ))
+    }
 
 
 }
@@ -0,0 +1,156 @@
+// Shared address vocabulary for erc20, from_address and to_address columns.
+// Every distinct address is stored once, without its '0x' prefix, and each
+// column keeps its own stream of vocabulary ids; a null address has no id.
+// With an address registry the vocabulary starts with the registered addresses,
+// and only the addresses following them are stored in the archive.
+
+use std::collections::HashMap;
+use crate::error::{BladeError, Result};
+use polars::prelude::*;
+use rayon::prelude::*;
+use super::streams::Streams;
+use super::hex_string::strip_hex_prefix;
+use super::address::PARALLEL_CHUNK_ROWS;
+use crate::transfers::registry::REGISTRY_STREAM;
+
+/// Columns sharing the vocabulary, with the name of their id stream.
+pub const VOCABULARY_COLUMNS: [(&str, &str); 3] = [
+    ("erc20", "erc20_ids"),
+    ("from_address", "from_address_ids"),
+    ("to_address", "to_address_ids"),
+];
+
+#[derive(Default)]
+pub struct SharedVocabularyCompressedAddressSeries {
+    pub vocabulary: Vec<String>,          // unique addresses without '0x', in first seen order
+    pub ids: Vec<Vec<Option<u32>>>,       // vocabulary id of every row, per `VOCABULARY_COLUMNS` entry
+    pub registered: Option<usize>,        // leading vocabulary addresses held by the address registry
+}
+
+impl SharedVocabularyCompressedAddressSeries {
+
+    pub fn new() -> Self {
+        Self {
+            vocabulary: Vec::new(),
+            ids: vec![Vec::new(); VOCABULARY_COLUMNS.len()],
+            registered: None,
+        }
+    }
+
+    /// Start the vocabulary from the addresses of a registry; they keep their
+    /// registry ids and are not stored in the archive.
+    pub fn with_registry(addresses: &[String]) -> Self {
+        let mut vocabulary_compression = Self::new();
+        vocabulary_compression.vocabulary = addresses.to_vec();
+        vocabulary_compression.registered = Some(addresses.len());
+        vocabulary_compression
+    }
+
+    /// Compress the address columns of a transfers dataset into one vocabulary,
+    /// extending the loaded vocabulary when appending.
+    pub fn compress(&mut self, dataset: &DataFrame) -> Result<()> {
+
+        // Strip and check every address; large columns are handled in parallel chunks
+        let mut stripped: Vec<Vec<Option<&str>>> = Vec::new();
+        for (name, _) in VOCABULARY_COLUMNS.iter() {
+            let values: Vec<Option<&str>> = dataset.column(name)?.str()?.iter().collect();
+            let addresses = values.par_chunks(PARALLEL_CHUNK_ROWS)
+                .enumerate()
+                .map(|(chunk, values)| {
+                    values.iter().enumerate()
+                        .map(|(i, value)| value.map(|address| strip_hex_prefix(address, name, chunk * PARALLEL_CHUNK_ROWS + i, Some(40))).transpose())
+                        .collect::<Result<Vec<Option<&str>>>>()
+                })
+                .collect::<Result<Vec<Vec<Option<&str>>>>>()?
+                .concat();
+            stripped.push(addresses);
+        }
+
+        // Assign ids column by column, continuing the loaded vocabulary
+        let mut address_to_id: HashMap<String, u32> = self.vocabulary.iter()
+            .enumerate()
+            .map(|(id, address)| (address.clone(), id as u32))
+            .collect();
+        for (ids, addresses) in self.ids.iter_mut().zip(stripped.iter()) {
+            for address in addresses.iter() {
+                let id = address.map(|address| {
+                    match address_to_id.get(address) {
+                        Some(id) => *id,
+                        None => {
+                            let id = self.vocabulary.len() as u32;
+                            address_to_id.insert(address.to_string(), id);
+                            self.vocabulary.push(address.to_string());
+                            id
+                        },
+                    }
+                });
+                ids.push(id);
+            }
+        }
+
+        Ok(())
+    }
+
+
+    pub fn create_compressed_df(&mut self, dataset: &DataFrame) -> Result<Vec<DataFrame>> {
+        // call compress function to create vocabulary / id references
+        self.compress(dataset)?;
+        // one dataframe per id stream, in `VOCABULARY_COLUMNS` order, then the vocabulary
+        let mut dataframes: Vec<DataFrame> = Vec::new();
+        for ((_, stream), ids) in VOCABULARY_COLUMNS.iter().zip(self.ids.iter()) {
+            dataframes.push(DataFrame::new(vec![Column::new((*stream).into(), ids)])?);
+        }
+        dataframes.push(DataFrame::new(vec![Column::new("address_vocabulary".into(), self.stored_vocabulary())])?);
+        Ok(dataframes)
+    }
+
+    /// Vocabulary addresses stored in the archive: those after the registered ones.
+    fn stored_vocabulary(&self) -> &[String] {
+        &self.vocabulary[self.registered.unwrap_or(0)..]
+    }
+
+    /// Load the vocabulary, starting from the registry addresses when the
+    /// archive was written against a registry.
+    fn load_vocabulary(&mut self, streams: &Streams) -> Result<()> {
+        self.vocabulary.clear();
+        self.registered = None;
+        if streams.contains(REGISTRY_STREAM) {
+            self.vocabulary.extend(streams.get(REGISTRY_STREAM)?.str()?.into_no_null_iter().map(str::to_string));
+            self.registered = Some(self.vocabulary.len());
+        }
+        self.vocabulary.extend(streams.get("address_vocabulary")?.str()?.into_no_null_iter().map(str::to_string));
+        Ok(())
+    }
+
+    /// Load the vocabulary and id streams from the archive, so that a later
+    /// `compress` call extends them.
+    pub fn load(&mut self, streams: &Streams) -> Result<()> {
+        self.load_vocabulary(streams)?;
+        for ((_, stream), ids) in VOCABULARY_COLUMNS.iter().zip(self.ids.iter_mut()) {
+            *ids = streams.get(stream)?.u32()?.iter().collect();
+        }
+        Ok(())
+    }
+
+    /// Decompress one of the vocabulary columns; only its id stream is read.
+    pub fn decompress(&mut self, streams: &Streams, column: &str) -> Result<Column> {
+        let (name, stream) = VOCABULARY_COLUMNS.iter()
+            .find(|(name, _)| *name == column)
+            .ok_or_else(|| BladeError::Codec(format!("column `{}` is not encoded by the address vocabulary", column)))?;
+        self.load_vocabulary(streams)?;
+
+        let mut decompressed: Vec<Option<String>> = Vec::with_capacity(streams.rows);
+        for id in streams.get(stream)?.u32()?.iter() {
+            let address = match id {
+                Some(id) => {
+                    let address = self.vocabulary.get(id as usize)
+                        .ok_or_else(|| BladeError::Integrity(format!("{} id {} is outside the vocabulary of {} addresses", name, id, self.vocabulary.len())))?;
+                    Some(format!("0x{}", address))
+                },
+                None => None,
+            };
+            decompressed.push(address);
+        }
+        Ok(Column::new((*name).into(), decompressed))
+    }
+}
@@ -0,0 +1,65 @@
+// Bit-packing of small integer ids.
+// Every value takes the same number of bits, the width of the largest id, and
+// values are packed LSB first into u32 words. The width is not stored: codecs
+// derive it from the size of the dictionary the ids point into.
+
+use crate::error::{BladeError, Result};
+
+/// Bits needed to store ids up to `max`; 0 when every id is 0.
+pub fn bit_width(max: u32) -> u8 {
+    (u32::BITS - max.leading_zeros()) as u8
+}
+
+/// Words holding `len` values of `width` bits.
+pub fn packed_words(len: usize, width: u8) -> usize {
+    (len * width as usize).div_ceil(32)
+}
+
+/// Pack `values` into words of `width` bits per value; values must fit the width.
+pub fn pack(values: &[u32], width: u8) -> Vec<u32> {
+    let mut words: Vec<u32> = vec![0; packed_words(values.len(), width)];
+    if width == 0 {
+        return words;
+    }
+    let width = width as usize;
+    for (i, value) in values.iter().enumerate() {
+        let bit = i * width;
+        let (word, shift) = (bit / 32, bit % 32);
+        words[word] |= value << shift;
+        if shift + width > 32 {
+            words[word + 1] |= value >> (32 - shift);
+        }
+    }
+    words
+}
+
+/// Unpack `len` values of `width` bits from words written by `pack`.
+pub fn unpack(words: &[u32], width: u8, len: usize) -> Result<Vec<u32>> {
+    unpack_range(words, width, 0, len)
+}
+
+/// Unpack the `len` values from value `first` on, of `width` bits, from words written by `pack`.
+pub fn unpack_range(words: &[u32], width: u8, first: usize, len: usize) -> Result<Vec<u32>> {
+    if width > 32 {
+        return Err(BladeError::Integrity(format!("bit width {} exceeds 32", width)));
+    }
+    if words.len() < packed_words(first + len, width) {
+        return Err(BladeError::Integrity(format!("{} packed words hold fewer than {} values of {} bits", words.len(), first + len, width)));
+    }
+    if width == 0 {
+        return Ok(vec![0; len]);
+    }
+    let mask: u64 = (1u64 << width) - 1;
+    let width = width as usize;
+    let mut values: Vec<u32> = Vec::with_capacity(len);
+    for i in first..first + len {
+        let bit = i * width;
+        let (word, shift) = (bit / 32, bit % 32);
+        let mut value = (words[word] as u64) >> shift;
+        if shift + width > 32 {
+            value |= (words[word + 1] as u64) << (32 - shift);
+        }
+        values.push((value & mask) as u32);
+    }
+    Ok(values)
+}
@@ -1,11 +1,11 @@
-use std::mem;
-use anyhow::Result;
+use crate::error::Result;
 use polars::prelude::*;
-use owo_colors::OwoColorize;
+use super::streams::Streams;
 
+#[derive(Default)]
 pub struct RLECompressedBlockNumberSeries {
-    pub values: Vec<u32>,    // Unique values in sequence
-    pub counts: Vec<u32>,    // Count of consecutive repetitions
+    pub values: Vec<Option<u32>>,    // Unique values in sequence, nulls included
+    pub counts: Vec<u32>,            // Count of consecutive repetitions
 }
 
 impl RLECompressedBlockNumberSeries {
@@ -18,10 +18,10 @@ impl RLECompressedBlockNumberSeries {
     }
 
     /// Compress block number column in transfers dataset through RLE methodology.
-    pub fn compress(&mut self, dataset: &DataFrame) -> Result<(Vec<u32>, Vec<u32>)> {
+    pub fn compress(&mut self, dataset: &DataFrame) -> Result<(Vec<Option<u32>>, Vec<u32>)> {
 
         // Distill block_number column from incoming dataset and convert to u32
-        let blocks = dataset.column("block_number").unwrap();
+        let blocks = dataset.column("block_number")?;
         let block_vec: Vec<Option<u32>> = blocks.u32()?.into_iter().collect();
 
         // Return empty tuple of vec if empty
@@ -30,34 +30,28 @@ impl RLECompressedBlockNumberSeries {
         }
 
         // Iterate with the first value of vector; set count to 1.
-        let mut current_value: u32 = block_vec[0].unwrap();
-        let mut current_count: u32 = 1;
+        // When appending, continue the last run loaded from the archive instead.
+        let (mut current_value, mut current_count, skip) = match (self.values.pop(), self.counts.pop()) {
+            (Some(value), Some(count)) => (value, count, 0),
+            _ => (block_vec[0], 1, 1),
+        };
 
         // Iterate through block_vec, skipping the first
-        // since that is set as current_value
-        for block in block_vec.iter().skip(1) {
-            let b = block.unwrap();
+        // since that is set as current_value (unless continuing a loaded run)
+        for block in block_vec.iter().skip(skip) {
+            let b = *block;
             if b == current_value {
-                current_count += 1 as u32;
+                current_count += 1;
             } else {
                 self.values.push(current_value);
-                self.counts.push(current_count.into());
+                self.counts.push(current_count);
 
                 current_value = b;
                 current_count = 1;
             }
         }
         self.values.push(current_value);
-        self.counts.push(current_count.into());
-
-        // Check size comparisons
-        let block_size = block_vec.capacity() * mem::size_of::<Option<u32>>();
-        let compressed_size = self.values.capacity() * mem::size_of::<u32>() + 
-                            self.counts.capacity() * mem::size_of::<u16>();
-        let compression_ratio = block_size as f64 / compressed_size as f64;
-
-        // Optional output print statements for comparison
-        println!("[BLOCK NUMBER MEM] {} → {} bytes ({:.2}x)", block_size.to_string().red(), compressed_size.to_string().green(), compression_ratio.to_string().bright_blue());
+        self.counts.push(current_count);
 
         // assert that output is equal in len to input
         // assert_eq!()
@@ -68,17 +62,33 @@ impl RLECompressedBlockNumberSeries {
 
     pub fn create_compressed_df(&mut self, dataset: &DataFrame) -> Result<DataFrame> {
         // call compress function to create value / count references
-        let _compressed_res = self.compress(dataset);
+        self.compress(dataset)?;
         let s1 = Column::new("block_values".into(), &self.values);
         let s2 = Column::new("block_counts".into(), &self.counts);
         let df = DataFrame::new(vec![s1, s2])?;
         Ok(df)
     }
 
-    /// Decompression of RLE compressed block number data in the transfer dataset.
-    pub fn decompress() -> Result<(), Box<dyn std::error::Error>> {
+    /// Load value / count references from the archive streams, so that a later
+    /// `compress` call continues the encoded runs.
+    pub fn load(&mut self, streams: &Streams) -> Result<()> {
+        self.values = streams.get("block_values")?.u32()?.iter().collect();
+        self.counts = streams.get("block_counts")?.u32()?.into_no_null_iter().collect();
         Ok(())
     }
+
+    /// Decompression of RLE compressed block number data in the transfer dataset.
+    pub fn decompress(&mut self, streams: &Streams) -> Result<Column> {
+        self.load(streams)?;
+        streams.check_runs("block_counts", self.values.len(), &self.counts)?;
+
+        // expand each value by its count of consecutive repetitions
+        let mut decompressed: Vec<Option<u32>> = Vec::with_capacity(streams.rows);
+        for (value, count) in self.values.iter().zip(self.counts.iter()) {
+            decompressed.extend(std::iter::repeat_n(*value, *count as usize));
+        }
+        Ok(Column::new("block_number".into(), decompressed))
+    }
 }
 
 
@@ -1,11 +1,11 @@
-use std::mem;
-use anyhow::Result;
+use crate::error::Result;
 use polars::prelude::*;
-use owo_colors::OwoColorize;
+use super::streams::Streams;
 
+#[derive(Default)]
 pub struct RLECompressedChainIdSeries {
-    pub values: Vec<u64>,    // Unique values in sequence
-    pub counts: Vec<u32>,    // Count of consecutive repetitions
+    pub values: Vec<Option<u64>>,    // Unique values in sequence, nulls included
+    pub counts: Vec<u32>,            // Count of consecutive repetitions
 }
 
 impl RLECompressedChainIdSeries {
@@ -18,10 +18,10 @@ impl RLECompressedChainIdSeries {
     }
 
     /// Compress chain_id column in transfers dataset through RLE methodology.
-    pub fn compress(&mut self, dataset: &DataFrame) -> Result<(Vec<u64>, Vec<u32>)> {
+    pub fn compress(&mut self, dataset: &DataFrame) -> Result<(Vec<Option<u64>>, Vec<u32>)> {
 
         // Distill chain_id column from incoming dataset and convert to u32
-        let chains = dataset.column("chain_id").unwrap();
+        let chains = dataset.column("chain_id")?;
         let chains_vec: Vec<Option<u64>> = chains.u64()?.into_iter().collect();
 
         // Return empty tuple of vec if empty
@@ -30,34 +30,28 @@ impl RLECompressedChainIdSeries {
         }
 
         // Iterate with the first value of vector; set count to 1.
-        let mut current_value: u64 = chains_vec[0].unwrap();
-        let mut current_count: u32 = 1;
+        // When appending, continue the last run loaded from the archive instead.
+        let (mut current_value, mut current_count, skip) = match (self.values.pop(), self.counts.pop()) {
+            (Some(value), Some(count)) => (value, count, 0),
+            _ => (chains_vec[0], 1, 1),
+        };
 
         // Iterate through chains_vec, skipping the first
-        // since that is set as current_value
-        for chain in chains_vec.iter().skip(1) {
-            let b = chain.unwrap();
+        // since that is set as current_value (unless continuing a loaded run)
+        for chain in chains_vec.iter().skip(skip) {
+            let b = *chain;
             if b == current_value {
-                current_count += 1 as u32;
+                current_count += 1;
             } else {
                 self.values.push(current_value);
-                self.counts.push(current_count.into());
+                self.counts.push(current_count);
 
                 current_value = b;
                 current_count = 1;
             }
         }
         self.values.push(current_value);
-        self.counts.push(current_count.into());
-
-        // Check size comparisons
-        let chain_size = chains_vec.capacity() * mem::size_of::<Option<u32>>();
-        let compressed_size = self.values.capacity() * mem::size_of::<u32>() + 
-                            self.counts.capacity() * mem::size_of::<u16>();
-        let compression_ratio = chain_size as f64 / compressed_size as f64;
-
-        // Optional output print statements for comparison
-        println!("[CHAIN ID MEM] {} → {} bytes ({:.2}x)", chain_size.to_string().red(), compressed_size.to_string().green(), compression_ratio.to_string().bright_blue());
+        self.counts.push(current_count);
 
         // assert that output is equal in len to input
         // assert_eq!()
@@ -68,17 +62,33 @@ impl RLECompressedChainIdSeries {
 
     pub fn create_compressed_df(&mut self, dataset: &DataFrame) -> Result<DataFrame> {
         // call compress function to create value / count references
-        let _compressed_res = self.compress(dataset);
+        self.compress(dataset)?;
         let s1 = Column::new("chain_id_values".into(), &self.values);
         let s2 = Column::new("chain_id_counts".into(), &self.counts);
         let df = DataFrame::new(vec![s1, s2])?;
         Ok(df)
     }
 
-    /// Decompression of RLE compressed block number data in the transfer dataset.
-    pub fn decompress() -> Result<(), Box<dyn std::error::Error>> {
+    /// Load value / count references from the archive streams, so that a later
+    /// `compress` call continues the encoded runs.
+    pub fn load(&mut self, streams: &Streams) -> Result<()> {
+        self.values = streams.get("chain_id_values")?.u64()?.iter().collect();
+        self.counts = streams.get("chain_id_counts")?.u32()?.into_no_null_iter().collect();
         Ok(())
     }
+
+    /// Decompression of RLE compressed chain id data in the transfer dataset.
+    pub fn decompress(&mut self, streams: &Streams) -> Result<Column> {
+        self.load(streams)?;
+        streams.check_runs("chain_id_counts", self.values.len(), &self.counts)?;
+
+        // expand each value by its count of consecutive repetitions
+        let mut decompressed: Vec<Option<u64>> = Vec::with_capacity(streams.rows);
+        for (value, count) in self.values.iter().zip(self.counts.iter()) {
+            decompressed.extend(std::iter::repeat_n(*value, *count as usize));
+        }
+        Ok(Column::new("chain_id".into(), decompressed))
+    }
 }
 
 
@@ -1,11 +1,14 @@
-use std::mem;
-use anyhow::Result;
+use std::collections::HashMap;
+use crate::error::{BladeError, Result};
 use polars::prelude::*;
-use owo_colors::OwoColorize;
+use super::streams::Streams;
+use super::address_vocabulary::SharedVocabularyCompressedAddressSeries;
+use super::bit_pack::{bit_width, pack, unpack};
 
+#[derive(Default)]
 pub struct RLECompressedErc20Series {
-    pub values: Vec<String>,    // Unique values in sequence
-    pub counts: Vec<u32>,       // Count of consecutive repetitions
+    pub values: Vec<Option<String>>,    // Unique values in sequence, nulls included
+    pub counts: Vec<u32>,               // Count of consecutive repetitions
 }
 
 impl RLECompressedErc20Series {
@@ -18,10 +21,10 @@ impl RLECompressedErc20Series {
     }
 
     /// Compress erc20 column in transfers dataset through RLE methodology.
-    pub fn compress(&mut self, dataset: &DataFrame) -> Result<(Vec<String>, Vec<u32>)> {
+    pub fn compress(&mut self, dataset: &DataFrame) -> Result<(Vec<Option<String>>, Vec<u32>)> {
 
         // Distill erc20 column from incoming dataset and convert to string
-        let tokens = dataset.column("erc20").unwrap();
+        let tokens = dataset.column("erc20")?;
         let token_strings_series: Vec<_> = tokens.str()?.into_iter().collect();
 
         // Return empty tuple of vec if empty
@@ -30,34 +33,27 @@ impl RLECompressedErc20Series {
         }
 
         // Iterate with the first value of vector; set count to 1.
-        let mut current_value: &str = token_strings_series[0].unwrap();
-        let mut current_count: u32 = 1;
+        // When appending, continue the last run loaded from the archive instead.
+        let (mut current_value, mut current_count, skip) = match (self.values.pop(), self.counts.pop()) {
+            (Some(value), Some(count)) => (value, count, 0),
+            _ => (token_strings_series[0].map(str::to_string), 1, 1),
+        };
 
         // Iterate through token_string_series, skipping the first
-        // since that is set as current_value
-        for token in token_strings_series.iter().skip(1) {
-            let b = token.unwrap();
-            if b == current_value {
-                current_count += 1 as u32;
+        // since that is set as current_value (unless continuing a loaded run)
+        for token in token_strings_series.iter().skip(skip) {
+            if *token == current_value.as_deref() {
+                current_count += 1;
             } else {
-                self.values.push(current_value.to_string());
-                self.counts.push(current_count.into());
+                self.values.push(current_value);
+                self.counts.push(current_count);
 
-                current_value = b;
+                current_value = token.map(str::to_string);
                 current_count = 1;
             }
         }
-        self.values.push(current_value.to_string());
-        self.counts.push(current_count.into());
-
-        // Check size comparisons
-        let token_size = token_strings_series.capacity() * mem::size_of::<Option<u32>>();
-        let compressed_size = self.values.capacity() * mem::size_of::<u32>() + 
-                            self.counts.capacity() * mem::size_of::<u16>();
-        let compression_ratio = token_size as f64 / compressed_size as f64;
-
-        // // Optional output print statements for comparison
-        println!("[ERC20 MEM] {} → {} bytes ({:.2}x)", token_size.to_string().red(), compressed_size.to_string().green(), compression_ratio.to_string().bright_blue());
+        self.values.push(current_value);
+        self.counts.push(current_count);
 
         // assert that output is equal in len to input
         // assert_eq!()
@@ -68,18 +64,124 @@ impl RLECompressedErc20Series {
 
     pub fn create_compressed_df(&mut self, dataset: &DataFrame) -> Result<DataFrame> {
         // call compress function to create value / count references
-        let _compressed_res = self.compress(dataset);
+        self.compress(dataset)?;
         let s1 = Column::new("token_values".into(), &self.values);
         let s2 = Column::new("token_counts".into(), &self.counts);
         let df = DataFrame::new(vec![s1, s2])?;
         Ok(df)
     }
 
-    /// Decompression of RLE compressed block number data in the transfer dataset.
-    pub fn decompress() -> Result<(), Box<dyn std::error::Error>> {
+    /// Load value / count references from the archive streams, so that a later
+    /// `compress` call continues the encoded runs.
+    pub fn load(&mut self, streams: &Streams) -> Result<()> {
+        self.values = streams.get("token_values")?.str()?.iter().map(|s| s.map(str::to_string)).collect();
+        self.counts = streams.get("token_counts")?.u32()?.into_no_null_iter().collect();
         Ok(())
     }
+
+    /// Decompression of RLE compressed erc20 data in the transfer dataset.
+    pub fn decompress(&mut self, streams: &Streams) -> Result<Column> {
+        self.load(streams)?;
+        streams.check_runs("token_counts", self.values.len(), &self.counts)?;
+
+        // expand each token by its count of consecutive repetitions
+        let mut decompressed: Vec<Option<&str>> = Vec::with_capacity(streams.rows);
+        for (value, count) in self.values.iter().zip(self.counts.iter()) {
+            decompressed.extend(std::iter::repeat_n(value.as_deref(), *count as usize));
+        }
+        Ok(Column::new("erc20".into(), decompressed))
+    }
+}
+
+
+#[derive(Default)]
+pub struct DictionaryCompressedErc20Series {
+    pub ids: Vec<u32>,                    // dictionary id of the token of each row
+    pub tokens: Vec<Option<String>>,      // unique tokens in first seen order; None for nulls
 }
 
+impl DictionaryCompressedErc20Series {
+
+    pub fn new() -> Self {
+        Self {
+            ids: Vec::new(),
+            tokens: Vec::new(),
+        }
+    }
+
+    /// Compress erc20 column in transfers dataset by dictionary encoding the token
+    /// addresses. Popular tokens interleave within a block, so runs of ids are
+    /// short; the ids are bit-packed instead, at the width of the largest id.
+    pub fn compress(&mut self, dataset: &DataFrame) -> Result<(Vec<u32>, Vec<Option<String>>)> {
+
+        // Distill erc20 column from incoming dataset
+        let tokens = dataset.column("erc20")?;
+        let token_strings_series = tokens.str()?;
+
+        // Continue the loaded dictionary when appending
+        let mut token_to_id: HashMap<Option<&str>, u32> = HashMap::new();
+        let mut new_tokens: Vec<Option<String>> = Vec::new();
+        for (id, token) in self.tokens.iter().enumerate() {
+            token_to_id.insert(token.as_deref(), id as u32);
+        }
+        let mut next_id = self.tokens.len() as u32;
+        for token in token_strings_series.iter() {
+            let id = *token_to_id.entry(token).or_insert_with(|| {
+                new_tokens.push(token.map(str::to_string));
+                next_id += 1;
+                next_id - 1
+            });
+            self.ids.push(id);
+        }
+        self.tokens.extend(new_tokens);
+
+        Ok((self.ids.clone(), self.tokens.clone()))
+    }
+
+    /// Bits per packed id: enough for the last id of the dictionary.
+    fn id_width(&self) -> u8 {
+        bit_width(self.tokens.len().saturating_sub(1) as u32)
+    }
+
+
+    pub fn create_compressed_df(&mut self, dataset: &DataFrame) -> Result<Vec<DataFrame>> {
+        // call compress function to create id / token references
+        self.compress(dataset)?;
+        let s1 = Column::new("token_ids_packed".into(), pack(&self.ids, self.id_width()));
+        let s2 = Column::new("token_dictionary".into(), &self.tokens);
+        Ok(vec![DataFrame::new(vec![s1])?, DataFrame::new(vec![s2])?])
+    }
+
+    /// Load ids and dictionary from the archive streams, so that a later
+    /// `compress` call extends them.
+    pub fn load(&mut self, streams: &Streams) -> Result<()> {
+        self.tokens = streams.get("token_dictionary")?.str()?.iter().map(|s| s.map(str::to_string)).collect();
+        let words: Vec<u32> = streams.get("token_ids_packed")?.u32()?.into_no_null_iter().collect();
+        self.ids = unpack(&words, self.id_width(), streams.rows)?;
+        Ok(())
+    }
 
+    /// Decompression of dictionary compressed erc20 data in the transfer dataset.
+    pub fn decompress(&mut self, streams: &Streams) -> Result<Column> {
+        self.load(streams)?;
 
+        let mut decompressed: Vec<Option<&str>> = Vec::with_capacity(streams.rows);
+        for id in self.ids.iter() {
+            let token = self.tokens.get(*id as usize)
+                .ok_or_else(|| BladeError::Integrity(format!("token id {} is not in the erc20 dictionary of {} tokens", id, self.tokens.len())))?;
+            decompressed.push(token.as_deref());
+        }
+        Ok(Column::new("erc20".into(), decompressed))
+    }
+}
+
+/// Decode the erc20 column with whichever codec wrote its streams.
+pub fn decompress_erc20(streams: &Streams) -> Result<Column> {
+    if streams.contains("address_vocabulary") {
+        return SharedVocabularyCompressedAddressSeries::new().decompress(streams, "erc20");
+    }
+    match streams.contains("token_dictionary") {
+        true => DictionaryCompressedErc20Series::new().decompress(streams),
+        false => RLECompressedErc20Series::new().decompress(streams),
+    }
+}
@@ -0,0 +1,27 @@
+use crate::error::{BladeError, Result};
+
+/// Strip the '0x' prefix of a hex encoded value (hash or address), checking
+/// that the rest is hex of `hex_len` characters when a length is given.
+pub fn strip_hex_prefix<'a>(value: &'a str, column: &str, row: usize, hex_len: Option<usize>) -> Result<&'a str> {
+    let hex = value.strip_prefix("0x")
+        .ok_or_else(|| BladeError::malformed(column, row, format!("`{}` is missing the 0x prefix", value)))?;
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(BladeError::malformed(column, row, format!("`{}` is not hex", value)));
+    }
+    if let Some(len) = hex_len {
+        if hex.len() != len {
+            return Err(BladeError::malformed(column, row, format!("`{}` has {} hex characters, expected {}", value, hex.len(), len)));
+        }
+    }
+    Ok(hex)
+}
+
+/// Strip the '0x' prefix of a transaction hash. Hashes are stored as bytes and
+/// decoded as lowercase hex, so uppercase digits are refused rather than lost.
+pub fn strip_hash_prefix<'a>(value: &'a str, column: &str, row: usize) -> Result<&'a str> {
+    let hex = strip_hex_prefix(value, column, row, None)?;
+    if hex.bytes().any(|b| b.is_ascii_uppercase()) {
+        return Err(BladeError::malformed(column, row, format!("`{}` is not lowercase hex, which hashes decode to", value)));
+    }
+    Ok(hex)
+}
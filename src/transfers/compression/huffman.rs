@@ -0,0 +1,191 @@
+// Canonical Huffman coding of u32 streams (indexes, counts).
+// Layout: symbol count (u32 LE), then per symbol in canonical order the symbol
+// (u32 LE) and its code length (u8), then the codes packed MSB first. The bytes
+// are stored as little endian u32 words, since a parquet binary value carries
+// costly min / max statistics.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use crate::error::{BladeError, Result};
+
+/// Longest code length; deeper trees are flattened by halving frequencies.
+const MAX_CODE_LEN: u8 = 32;
+
+/// Huffman encode `values` into self-describing u32 words.
+pub fn encode(values: &[u32]) -> Vec<u32> {
+    encode_bytes(values)
+        .chunks(4)
+        .map(|chunk| {
+            let mut word = [0u8; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            u32::from_le_bytes(word)
+        })
+        .collect()
+}
+
+/// Decode `len` values from words written by `encode`.
+pub fn decode(words: &[u32], len: usize) -> Result<Vec<u32>> {
+    let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+    decode_bytes(&bytes, len)
+}
+
+fn encode_bytes(values: &[u32]) -> Vec<u8> {
+    let mut frequencies: HashMap<u32, u64> = HashMap::new();
+    for value in values {
+        *frequencies.entry(*value).or_insert(0) += 1;
+    }
+    let lengths = code_lengths(&frequencies);
+    let (symbols, codes) = canonical_codes(&lengths);
+
+    let mut out: Vec<u8> = Vec::with_capacity(4 + symbols.len() * 5 + values.len() / 2);
+    out.extend_from_slice(&(symbols.len() as u32).to_le_bytes());
+    for (symbol, len) in symbols.iter() {
+        out.extend_from_slice(&symbol.to_le_bytes());
+        out.push(*len);
+    }
+
+    // pack codes MSB first into bytes
+    let table: HashMap<u32, (u64, u8)> = symbols.iter().zip(codes.iter())
+        .map(|((symbol, len), code)| (*symbol, (*code, *len)))
+        .collect();
+    let mut buffer: u64 = 0;
+    let mut bits: u8 = 0;
+    for value in values {
+        let (code, len) = table[value];
+        for shift in (0..len).rev() {
+            buffer = (buffer << 1) | ((code >> shift) & 1);
+            bits += 1;
+            if bits == 8 {
+                out.push(buffer as u8);
+                buffer = 0;
+                bits = 0;
+            }
+        }
+    }
+    if bits > 0 {
+        out.push((buffer << (8 - bits)) as u8);
+    }
+    out
+}
+
+fn decode_bytes(bytes: &[u8], len: usize) -> Result<Vec<u32>> {
+    let truncated = || BladeError::Integrity("truncated huffman stream".to_string());
+
+    let count = u32::from_le_bytes(bytes.get(0..4).ok_or_else(truncated)?.try_into().expect("range of 4 bytes")) as usize;
+    // every table entry takes 5 bytes; bound the header before allocating for it
+    if count > (bytes.len() - 4) / 5 {
+        return Err(truncated());
+    }
+    let mut symbols: Vec<u32> = Vec::with_capacity(count);
+    let mut length_counts: Vec<u64> = vec![0; MAX_CODE_LEN as usize + 1];
+    let mut offset = 4;
+    for _ in 0..count {
+        let entry = bytes.get(offset..offset + 5).ok_or_else(truncated)?;
+        symbols.push(u32::from_le_bytes(entry[0..4].try_into().expect("range of 4 bytes")));
+        let code_len = entry[4];
+        if code_len == 0 || code_len > MAX_CODE_LEN {
+            return Err(BladeError::Integrity(format!("invalid huffman code length {}", code_len)));
+        }
+        length_counts[code_len as usize] += 1;
+        offset += 5;
+    }
+
+    // first canonical code and first symbol position of every code length
+    let mut first_code: Vec<u64> = vec![0; MAX_CODE_LEN as usize + 1];
+    let mut first_index: Vec<u64> = vec![0; MAX_CODE_LEN as usize + 1];
+    let (mut code, mut index) = (0u64, 0u64);
+    for len in 1..=MAX_CODE_LEN as usize {
+        code = (code + length_counts[len - 1]) << 1;
+        first_code[len] = code;
+        first_index[len] = index;
+        index += length_counts[len];
+    }
+
+    // every value takes at least one bit of payload
+    if len > (bytes.len() - offset).saturating_mul(8) {
+        return Err(truncated());
+    }
+    let mut values: Vec<u32> = Vec::with_capacity(len);
+    let mut code: u64 = 0;
+    let mut code_len: usize = 0;
+    'bytes: for byte in &bytes[offset..] {
+        for shift in (0..8).rev() {
+            if values.len() == len {
+                break 'bytes;
+            }
+            code = (code << 1) | ((*byte >> shift) & 1) as u64;
+            code_len += 1;
+            if code_len > MAX_CODE_LEN as usize {
+                return Err(BladeError::Integrity("invalid huffman code".to_string()));
+            }
+            let position = code.wrapping_sub(first_code[code_len]);
+            if code >= first_code[code_len] && position < length_counts[code_len] {
+                values.push(symbols[(first_index[code_len] + position) as usize]);
+                code = 0;
+                code_len = 0;
+            }
+        }
+    }
+    if values.len() != len {
+        return Err(truncated());
+    }
+    Ok(values)
+}
+
+/// Code length of every symbol, limited to `MAX_CODE_LEN`.
+fn code_lengths(frequencies: &HashMap<u32, u64>) -> HashMap<u32, u8> {
+    if frequencies.len() == 1 {
+        return frequencies.keys().map(|symbol| (*symbol, 1)).collect();
+    }
+
+    let mut weights: Vec<(u32, u64)> = frequencies.iter().map(|(s, f)| (*s, *f)).collect();
+    weights.sort_unstable();
+    loop {
+        // nodes: leaves first, then merged parents; track each node's parent
+        let mut parents: Vec<usize> = vec![usize::MAX; weights.len()];
+        let mut heap: BinaryHeap<Reverse<(u64, usize)>> = weights.iter().enumerate()
+            .map(|(i, (_, weight))| Reverse((*weight, i)))
+            .collect();
+        while heap.len() > 1 {
+            let (Some(Reverse((w1, n1))), Some(Reverse((w2, n2)))) = (heap.pop(), heap.pop()) else {
+                break;
+            };
+            let node = parents.len();
+            parents.push(usize::MAX);
+            parents[n1] = node;
+            parents[n2] = node;
+            heap.push(Reverse((w1 + w2, node)));
+        }
+
+        // depth of each leaf; parents always come after their children
+        let mut depths: Vec<u8> = vec![0; parents.len()];
+        for node in (0..parents.len()).rev() {
+            if parents[node] != usize::MAX {
+                depths[node] = depths[parents[node]].saturating_add(1);
+            }
+        }
+        if depths[..weights.len()].iter().all(|d| *d <= MAX_CODE_LEN) {
+            return weights.iter().zip(depths.iter()).map(|((symbol, _), depth)| (*symbol, *depth)).collect();
+        }
+        for (_, weight) in weights.iter_mut() {
+            *weight = (*weight).div_ceil(2);
+        }
+    }
+}
+
+/// Symbols in canonical order (by code length, then symbol) with their codes.
+fn canonical_codes(lengths: &HashMap<u32, u8>) -> (Vec<(u32, u8)>, Vec<u64>) {
+    let mut symbols: Vec<(u32, u8)> = lengths.iter().map(|(s, l)| (*s, *l)).collect();
+    symbols.sort_unstable_by_key(|(symbol, len)| (*len, *symbol));
+
+    let mut codes: Vec<u64> = Vec::with_capacity(symbols.len());
+    let mut code: u64 = 0;
+    let mut previous_len: u8 = symbols.first().map(|(_, len)| *len).unwrap_or(0);
+    for (_, len) in symbols.iter() {
+        code <<= len - previous_len;
+        codes.push(code);
+        code += 1;
+        previous_len = *len;
+    }
+    (symbols, codes)
+}
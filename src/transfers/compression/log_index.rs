@@ -1,11 +1,11 @@
-use std::mem;
-use anyhow::Result;
+use crate::error::Result;
 use polars::prelude::*;
-use owo_colors::OwoColorize;
+use super::streams::Streams;
 
+#[derive(Default)]
 pub struct RLECompressedLogIndexSeries {
-    pub values: Vec<u32>,    // Unique values in sequence
-    pub counts: Vec<u32>,    // Count of consecutive repetitions
+    pub values: Vec<Option<u32>>,    // Unique values in sequence, nulls included
+    pub counts: Vec<u32>,            // Count of consecutive repetitions
 }
 
 impl RLECompressedLogIndexSeries {
@@ -18,10 +18,10 @@ impl RLECompressedLogIndexSeries {
     }
 
     /// Compress log index number column in transfers dataset through RLE methodology.
-    pub fn compress(&mut self, dataset: &DataFrame) -> Result<(Vec<u32>, Vec<u32>)> {
+    pub fn compress(&mut self, dataset: &DataFrame) -> Result<(Vec<Option<u32>>, Vec<u32>)> {
 
         // Distill log_index column from incoming dataset and convert to u32
-        let log_index = dataset.column("log_index").unwrap();
+        let log_index = dataset.column("log_index")?;
         let log_index_vec: Vec<Option<u32>> = log_index.u32()?.into_iter().collect();
 
         // Return empty tuple of vec if empty
@@ -30,34 +30,28 @@ impl RLECompressedLogIndexSeries {
         }
 
         // Iterate with the first value of vector; set count to 1.
-        let mut current_value: u32  = log_index_vec[0].unwrap();
-        let mut current_count: u32 = 1;
+        // When appending, continue the last run loaded from the archive instead.
+        let (mut current_value, mut current_count, skip) = match (self.values.pop(), self.counts.pop()) {
+            (Some(value), Some(count)) => (value, count, 0),
+            _ => (log_index_vec[0], 1, 1),
+        };
 
         // Iterate through log_index_vec, skipping the first
-        // since that is set as current_value
-        for log in log_index_vec.iter().skip(1) {
-            let b = log.unwrap();
+        // since that is set as current_value (unless continuing a loaded run)
+        for log in log_index_vec.iter().skip(skip) {
+            let b = *log;
             if b == current_value {
-                current_count += 1 as u32;
+                current_count += 1;
             } else {
                 self.values.push(current_value);
-                self.counts.push(current_count.into());
+                self.counts.push(current_count);
 
                 current_value = b;
                 current_count = 1;
             }
         }
         self.values.push(current_value);
-        self.counts.push(current_count.into());
-
-        // Check size comparisons
-        let log_index_size = log_index_vec.capacity() * mem::size_of::<Option<u32>>();
-        let compressed_size = self.values.capacity() * mem::size_of::<u32>() + 
-                            self.counts.capacity() * mem::size_of::<u16>();
-        let compression_ratio = log_index_size as f64 / compressed_size as f64;
-
-        // // Optional output print statements for comparison
-        println!("[LOG INDEX MEM] {} → {} bytes ({:.2}x)", log_index_size.to_string().red(), compressed_size.to_string().green(), compression_ratio.to_string().bright_blue());
+        self.counts.push(current_count);
 
 
         // assert that output is equal in len to input
@@ -69,17 +63,33 @@ impl RLECompressedLogIndexSeries {
 
     pub fn create_compressed_df(&mut self, dataset: &DataFrame) -> Result<DataFrame> {
         // call compress function to create value / count references
-        let _compressed_res = self.compress(dataset);
+        self.compress(dataset)?;
         let s1 = Column::new("log_index_values".into(), &self.values);
         let s2 = Column::new("log_index_counts".into(), &self.counts);
         let df = DataFrame::new(vec![s1, s2])?;
         Ok(df)
     }
 
-    /// Decompression of RLE compressed block number data in the transfer dataset.
-    pub fn decompress() -> Result<(), Box<dyn std::error::Error>> {
+    /// Load value / count references from the archive streams, so that a later
+    /// `compress` call continues the encoded runs.
+    pub fn load(&mut self, streams: &Streams) -> Result<()> {
+        self.values = streams.get("log_index_values")?.u32()?.iter().collect();
+        self.counts = streams.get("log_index_counts")?.u32()?.into_no_null_iter().collect();
         Ok(())
     }
+
+    /// Decompression of RLE compressed log index data in the transfer dataset.
+    pub fn decompress(&mut self, streams: &Streams) -> Result<Column> {
+        self.load(streams)?;
+        streams.check_runs("log_index_counts", self.values.len(), &self.counts)?;
+
+        // expand each value by its count of consecutive repetitions
+        let mut decompressed: Vec<Option<u32>> = Vec::with_capacity(streams.rows);
+        for (value, count) in self.values.iter().zip(self.counts.iter()) {
+            decompressed.extend(std::iter::repeat_n(*value, *count as usize));
+        }
+        Ok(Column::new("log_index".into(), decompressed))
+    }
 }
 
 
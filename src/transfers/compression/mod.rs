@@ -8,30 +8,36 @@ pub mod log_index;
 pub use log_index::RLECompressedLogIndexSeries;
 
 pub mod transaction_hash;
-pub use transaction_hash::DictionaryCompressedTransactionHashSeries;
+pub use transaction_hash::{RLECompressedTransactionHashSeries, DictionaryCompressedTransactionHashSeries, KeyedCompressedTransactionHashSeries};
 
 pub mod erc20;
-pub use erc20::RLECompressedErc20Series;
-
-
-pub mod from_address;
-// pub use from_address::RLECompressedFromAddressSeries;
-pub use from_address::DictionaryCompressedFromAddressSeries;
-
-pub mod to_address;
-// pub use to_address::RLECompressedToAddressSeries;
-pub use to_address::DictionaryCompressedToAddressSeries;
+pub use erc20::{RLECompressedErc20Series, DictionaryCompressedErc20Series};
 
 
 pub mod address;
 pub use address::DictionaryCompressedAddressSeries;
 
+pub mod address_vocabulary;
+pub use address_vocabulary::SharedVocabularyCompressedAddressSeries;
+
 
 pub mod chain_id;
 pub use chain_id::RLECompressedChainIdSeries;
 
 
 pub mod value_string;
-pub use value_string::NormalizedCompressedValueStrings;
+pub use value_string::MantissaExponentCompressedValueStrings;
+
+pub mod value_dictionary;
+pub use value_dictionary::HotDictionaryCompressedValueStrings;
+
+pub mod streams;
+pub use streams::Streams;
+
+pub mod hex_string;
+
+pub mod huffman;
+
+pub mod bit_pack;
 
 // pub mod utils;
\ No newline at end of file
@@ -0,0 +1,45 @@
+use crate::error::{BladeError, Result};
+use polars::prelude::*;
+use std::collections::HashMap;
+
+/// Encoded streams of a BLADE archive, trimmed to their true lengths.
+pub struct Streams {
+    pub rows: usize,                          // row count of the original dataset
+    pub columns: HashMap<String, Column>,     // stream name -> stream column
+}
+
+impl Streams {
+
+    pub fn new(rows: usize) -> Self {
+        Self {
+            rows,
+            columns: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, column: Column) {
+        self.columns.insert(column.name().to_string(), column);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.columns.contains_key(name)
+    }
+
+    /// Fetch a stream by name, erroring if the archive does not carry it.
+    pub fn get(&self, name: &str) -> Result<&Column> {
+        self.columns.get(name).ok_or_else(|| BladeError::Format(format!("missing stream `{}`", name)))
+    }
+
+    /// Check, before expanding them, that the run counts of a run-length encoded
+    /// column pair up with its values and add up to exactly the archive's rows.
+    pub fn check_runs(&self, counts_stream: &str, values: usize, counts: &[u32]) -> Result<()> {
+        if values != counts.len() {
+            return Err(BladeError::Integrity(format!("{} run values for {} counts in `{}`", values, counts.len(), counts_stream)));
+        }
+        let covered: u64 = counts.iter().map(|count| *count as u64).sum();
+        if covered != self.rows as u64 {
+            return Err(BladeError::Integrity(format!("runs of `{}` cover {} rows, expected {}", counts_stream, covered, self.rows)));
+        }
+        Ok(())
+    }
+}
@@ -1,53 +1,55 @@
-use hex::{decode, encode};
-use std::mem;
-use anyhow::Result;
+use crate::error::{BladeError, Result};
 use polars::prelude::*;
+use super::streams::Streams;
+use super::hex_string::strip_hash_prefix;
+use super::bit_pack::{bit_width, pack, unpack};
+use super::block_number::RLECompressedBlockNumberSeries;
+use super::transaction_index::RLECompressedTransactionIndexSeries;
 use std::collections::HashMap;
-use owo_colors::OwoColorize;
-use std::collections::HashSet;
 
 
-pub struct DictionaryCompressedTransactionHashSeries {
-    // pub unique_hashes: HashMap<u32, Vec<u8>>,   // Unique transaction hashes
-    pub index: Vec<u32>,
-    pub hashes: Vec<Vec<u8>>,
-    // pub hash_dataframe: DataFrame,
+#[derive(Default)]
+pub struct RLECompressedTransactionHashSeries {
+    pub index: Vec<u32>,               // row where each run of one hash starts
+    pub hashes: Vec<Option<Vec<u8>>>,  // hash of each run; None for a run of nulls
+    pub rows: usize,                   // rows encoded so far; offsets appended run starts
 }
 
-impl DictionaryCompressedTransactionHashSeries {
+impl RLECompressedTransactionHashSeries {
 
     pub fn new() -> Self {
         Self {
-            // unique_hashes: HashMap::new(),
             index: Vec::new(),
             hashes: Vec::new(),
-            // hash_dataframe: DataFrame::default(),
+            rows: 0,
         }
     }
 
+    /// Compress transaction_hash column in transfers dataset into runs of one hash.
     pub fn compress(&mut self, dataset: &DataFrame) -> Result<()> {
 
-        let tx_hashes = dataset.column("transaction_hash").unwrap();
-
-        let mut seen: HashSet<Vec<u8>> = HashSet::new();
-        let tx_hash_series = tx_hashes.str().unwrap();
+        let tx_hashes = dataset.column("transaction_hash")?;
+        let tx_hash_series = tx_hashes.str()?;
 
+        // Logs of one transaction are consecutive; store the hash once per run
+        // along with the row position where the run starts. When appending,
+        // the last loaded run is continued. Nulls form runs of their own.
+        let mut previous: Option<Option<Vec<u8>>> = self.hashes.last().cloned();
         for (index, item) in tx_hash_series.iter().enumerate() {
-            let val = item.unwrap();
-            let formatted_val = &val[2..];
-            let hex_string = hex::decode(formatted_val).unwrap();
-            if seen.insert(hex_string.clone()) {
-                self.index.push(index as u32);
-                self.hashes.push(hex_string);
+            let hex_string = match item {
+                Some(val) => {
+                    let hex = strip_hash_prefix(val, "transaction_hash", index)?;
+                    Some(hex::decode(hex).map_err(|e| BladeError::malformed("transaction_hash", index, e.to_string()))?)
+                },
+                None => None,
+            };
+            if previous.as_ref() != Some(&hex_string) {
+                self.index.push((self.rows + index) as u32);
+                self.hashes.push(hex_string.clone());
+                previous = Some(hex_string);
             }
         }
-
-        // Output stats to terminal
-        let uncompressed_mem_size = tx_hashes.len() * std::mem::size_of::<polars::datatypes::AnyValue>();
-        let compressed_size = self.index.capacity() * mem::size_of::<u32>() + 
-                            self.hashes.capacity() * mem::size_of::<u16>();
-        let compression_ratio = uncompressed_mem_size as f64 / compressed_size as f64;
-        println!("[TX HASH MEM] {} → {} bytes ({:.2}x)", uncompressed_mem_size.to_string().red(), compressed_size.to_string().green(), compression_ratio.to_string().bright_blue());
+        self.rows += tx_hash_series.len();
 
         Ok(())
     }
@@ -55,25 +57,243 @@ impl DictionaryCompressedTransactionHashSeries {
 
     pub fn create_compressed_df(&mut self, dataset: &DataFrame) -> Result<DataFrame> {
         // call compress function to create value / count references
-        let _compressed_res = self.compress(dataset);
+        self.compress(dataset)?;
         let s1 = Column::new("tx_hash_index".into(), &self.index);
         let s2 = Column::new("tx_hash_values".into(), &self.hashes);
         let df = DataFrame::new(vec![s1, s2])?;
-
-        let compressed_mem_size: usize = df.get_columns()
-            .iter()
-            .map(|col| col.len() * std::mem::size_of::<polars::datatypes::AnyValue>())
-            .sum();
-        // println!("compressed tx hash size: {:?}", compressed_mem_size);
         Ok(df)
     }
 
-    // pub fn decompress(&mut self) -> Result<()> {
-    //     // decode 
-    //     let reconstructed = format!("0x{}", hex::encode(&hash_bytes));
-    //     Ok(())
-    // }
+    /// Load run starts and hashes from the archive streams, so that a later
+    /// `compress` call continues after the archived rows.
+    pub fn load(&mut self, streams: &Streams) -> Result<()> {
+        self.index = streams.get("tx_hash_index")?.u32()?.into_no_null_iter().collect();
+        self.hashes = streams.get("tx_hash_values")?.binary()?.iter().map(|h| h.map(|h| h.to_vec())).collect();
+        self.rows = streams.rows;
+        Ok(())
+    }
+
+    /// Decompression of run start / hash references back into one hash per row.
+    pub fn decompress(&mut self, streams: &Streams) -> Result<Column> {
+        self.load(streams)?;
+        self.check_run_starts()?;
 
+        // each hash covers the rows up to the start of the next run
+        let mut decompressed: Vec<Option<String>> = Vec::with_capacity(streams.rows);
+        for (i, hash_bytes) in self.hashes.iter().enumerate() {
+            let end = self.index.get(i + 1).map(|e| *e as usize).unwrap_or(streams.rows);
+            let reconstructed = hash_bytes.as_ref().map(|h| format!("0x{}", hex::encode(h)));
+            while decompressed.len() < end {
+                decompressed.push(reconstructed.clone());
+            }
+        }
+        Ok(Column::new("transaction_hash".into(), decompressed))
+    }
 
+    /// Check that the loaded runs start at row 0, one per hash, in increasing
+    /// order and inside the archive's rows.
+    pub fn check_run_starts(&self) -> Result<()> {
+        if self.index.len() != self.hashes.len() {
+            return Err(BladeError::Integrity(format!("{} run starts for {} hashes in `tx_hash_index`", self.index.len(), self.hashes.len())));
+        }
+        let ordered = self.index.first().map_or(self.rows == 0, |first| *first == 0)
+            && self.index.windows(2).all(|pair| pair[0] < pair[1])
+            && self.index.last().is_none_or(|last| (*last as usize) < self.rows);
+        if !ordered {
+            return Err(BladeError::Integrity(format!("runs of `tx_hash_index` do not cover the {} rows in order", self.rows)));
+        }
+        Ok(())
+    }
+}
+
+
+#[derive(Default)]
+pub struct DictionaryCompressedTransactionHashSeries {
+    pub ids: Vec<u32>,                 // dictionary id of the hash of each row
+    pub hashes: Vec<Option<Vec<u8>>>,  // unique hashes in first occurrence order; None for nulls
 }
 
+impl DictionaryCompressedTransactionHashSeries {
+
+    pub fn new() -> Self {
+        Self {
+            ids: Vec::new(),
+            hashes: Vec::new(),
+        }
+    }
+
+    /// Compress transaction_hash column in transfers dataset by storing every
+    /// distinct hash once, in first occurrence order, with the bit-packed id of
+    /// each row. Unlike runs, a hash recurring later in the dataset is not stored
+    /// again. When appending, the loaded dictionary is extended.
+    pub fn compress(&mut self, dataset: &DataFrame) -> Result<()> {
+
+        let tx_hashes = dataset.column("transaction_hash")?;
+        let tx_hash_series = tx_hashes.str()?;
+
+        let mut hash_to_id: HashMap<Option<Vec<u8>>, u32> = self.hashes.iter()
+            .enumerate()
+            .map(|(id, hash)| (hash.clone(), id as u32))
+            .collect();
+        for (index, item) in tx_hash_series.iter().enumerate() {
+            let hex_string = match item {
+                Some(val) => {
+                    let hex = strip_hash_prefix(val, "transaction_hash", index)?;
+                    Some(hex::decode(hex).map_err(|e| BladeError::malformed("transaction_hash", index, e.to_string()))?)
+                },
+                None => None,
+            };
+            let id = match hash_to_id.get(&hex_string) {
+                Some(id) => *id,
+                None => {
+                    let id = self.hashes.len() as u32;
+                    hash_to_id.insert(hex_string.clone(), id);
+                    self.hashes.push(hex_string);
+                    id
+                },
+            };
+            self.ids.push(id);
+        }
+
+        Ok(())
+    }
+
+    /// Bits per packed id: enough for the last id of the dictionary.
+    fn id_width(&self) -> u8 {
+        bit_width(self.hashes.len().saturating_sub(1) as u32)
+    }
+
+    pub fn create_compressed_df(&mut self, dataset: &DataFrame) -> Result<Vec<DataFrame>> {
+        // call compress function to create id / hash references
+        self.compress(dataset)?;
+        let s1 = Column::new("tx_hash_ids_packed".into(), pack(&self.ids, self.id_width()));
+        let s2 = Column::new("tx_hash_dictionary".into(), &self.hashes);
+        Ok(vec![DataFrame::new(vec![s1])?, DataFrame::new(vec![s2])?])
+    }
+
+    /// Load ids and dictionary from the archive streams, so that a later
+    /// `compress` call extends them.
+    pub fn load(&mut self, streams: &Streams) -> Result<()> {
+        self.hashes = streams.get("tx_hash_dictionary")?.binary()?.iter().map(|h| h.map(|h| h.to_vec())).collect();
+        let words: Vec<u32> = streams.get("tx_hash_ids_packed")?.u32()?.into_no_null_iter().collect();
+        self.ids = unpack(&words, self.id_width(), streams.rows)?;
+        Ok(())
+    }
+
+    /// Decompression of dictionary ids back into one hash per row.
+    pub fn decompress(&mut self, streams: &Streams) -> Result<Column> {
+        self.load(streams)?;
+
+        let mut decompressed: Vec<Option<String>> = Vec::with_capacity(streams.rows);
+        for id in self.ids.iter() {
+            let hash = self.hashes.get(*id as usize)
+                .ok_or_else(|| BladeError::Integrity(format!("transaction hash id {} is outside the dictionary of {} hashes", id, self.hashes.len())))?;
+            decompressed.push(hash.as_ref().map(|h| format!("0x{}", hex::encode(h))));
+        }
+        Ok(Column::new("transaction_hash".into(), decompressed))
+    }
+}
+
+
+/// (block_number, transaction_index) of a row; together they identify a transaction.
+pub type TransactionKey = (Option<u32>, Option<u32>);
+
+#[derive(Default)]
+pub struct KeyedCompressedTransactionHashSeries {
+    pub hashes: Vec<Option<Vec<u8>>>,              // hash of each distinct key, first seen order
+    pub keys: HashMap<TransactionKey, u32>,        // key -> position of its hash
+}
+
+impl KeyedCompressedTransactionHashSeries {
+
+    pub fn new() -> Self {
+        Self {
+            hashes: Vec::new(),
+            keys: HashMap::new(),
+        }
+    }
+
+    /// Store the hash of every distinct (block_number, transaction_index) once;
+    /// rows are linked to it through those columns, so no per-row stream is kept.
+    /// Returns false when a key maps to two different hashes.
+    pub fn compress(&mut self, dataset: &DataFrame) -> Result<bool> {
+
+        let blocks = dataset.column("block_number")?.u32()?;
+        let transaction_indexes = dataset.column("transaction_index")?.u32()?;
+        let tx_hash_series = dataset.column("transaction_hash")?.str()?;
+
+        for (row, ((block, transaction_index), item)) in blocks.iter().zip(transaction_indexes.iter()).zip(tx_hash_series.iter()).enumerate() {
+            let hash = match item {
+                Some(val) => {
+                    let hex = strip_hash_prefix(val, "transaction_hash", row)?;
+                    Some(hex::decode(hex).map_err(|e| BladeError::malformed("transaction_hash", row, e.to_string()))?)
+                },
+                None => None,
+            };
+            match self.keys.get(&(block, transaction_index)) {
+                Some(position) => {
+                    if self.hashes[*position as usize] != hash {
+                        return Ok(false);
+                    }
+                },
+                None => {
+                    self.keys.insert((block, transaction_index), self.hashes.len() as u32);
+                    self.hashes.push(hash);
+                },
+            }
+        }
+
+        Ok(true)
+    }
+
+
+    /// Compressed hashes of `dataset`; `None` when block_number and
+    /// transaction_index do not determine the hash.
+    pub fn create_compressed_df(&mut self, dataset: &DataFrame) -> Result<Option<DataFrame>> {
+        if !self.compress(dataset)? {
+            return Ok(None);
+        }
+        let s1 = Column::new("tx_hash_keyed_values".into(), &self.hashes);
+        Ok(Some(DataFrame::new(vec![s1])?))
+    }
+
+    /// Load the hashes from the archive streams and link them to their keys,
+    /// decoding block_number and transaction_index, so that a later `compress`
+    /// call continues them.
+    pub fn load(&mut self, streams: &Streams) -> Result<()> {
+        self.hashes = streams.get("tx_hash_keyed_values")?.binary()?.iter().map(|h| h.map(|h| h.to_vec())).collect();
+        self.keys.clear();
+        for key in transaction_keys(streams)? {
+            let next = self.keys.len() as u32;
+            self.keys.entry(key).or_insert(next);
+        }
+        if self.keys.len() != self.hashes.len() {
+            return Err(BladeError::Integrity(format!("{} transaction keys for {} keyed hashes", self.keys.len(), self.hashes.len())));
+        }
+        Ok(())
+    }
+
+    /// Decompression of keyed hashes back into one hash per row.
+    pub fn decompress(&mut self, streams: &Streams) -> Result<Column> {
+        self.hashes = streams.get("tx_hash_keyed_values")?.binary()?.iter().map(|h| h.map(|h| h.to_vec())).collect();
+        self.keys.clear();
+
+        // keys take the hashes in first seen order, as when compressing
+        let mut decompressed: Vec<Option<String>> = Vec::with_capacity(streams.rows);
+        for key in transaction_keys(streams)? {
+            let next = self.keys.len() as u32;
+            let position = *self.keys.entry(key).or_insert(next);
+            let hash_bytes = self.hashes.get(position as usize)
+                .ok_or_else(|| BladeError::Integrity(format!("transaction {:?} has no hash among {} keyed hashes", key, self.hashes.len())))?;
+            decompressed.push(hash_bytes.as_ref().map(|h| format!("0x{}", hex::encode(h))));
+        }
+        Ok(Column::new("transaction_hash".into(), decompressed))
+    }
+}
+
+/// (block_number, transaction_index) of every archived row, decoded from their streams.
+pub fn transaction_keys(streams: &Streams) -> Result<Vec<TransactionKey>> {
+    let blocks = RLECompressedBlockNumberSeries::new().decompress(streams)?;
+    let transaction_indexes = RLECompressedTransactionIndexSeries::new().decompress(streams)?;
+    Ok(blocks.u32()?.iter().zip(transaction_indexes.u32()?.iter()).collect())
+}
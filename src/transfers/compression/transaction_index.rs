@@ -1,11 +1,11 @@
-use std::mem;
-use anyhow::Result;
+use crate::error::Result;
 use polars::prelude::*;
-use owo_colors::OwoColorize;
+use super::streams::Streams;
 
+#[derive(Default)]
 pub struct RLECompressedTransactionIndexSeries {
-    pub values: Vec<u32>,    // Unique values in sequence
-    pub counts: Vec<u32>,    // Count of consecutive repetitions
+    pub values: Vec<Option<u32>>,    // Unique values in sequence, nulls included
+    pub counts: Vec<u32>,            // Count of consecutive repetitions
 }
 
 impl RLECompressedTransactionIndexSeries {
@@ -17,9 +17,9 @@ impl RLECompressedTransactionIndexSeries {
         }
     }
 
-    pub fn compress(&mut self, dataset: &DataFrame) -> Result<(Vec<u32>, Vec<u32>)> {
+    pub fn compress(&mut self, dataset: &DataFrame) -> Result<(Vec<Option<u32>>, Vec<u32>)> {
         // establish incoming col len // let num_rows = dataset.height();
-        let transaction_index = dataset.column("transaction_index").unwrap();
+        let transaction_index = dataset.column("transaction_index")?;
         let transaction_index_vec: Vec<Option<u32>> = transaction_index.u32()?.into_iter().collect();
 
         // early return if vec is empty
@@ -28,34 +28,27 @@ impl RLECompressedTransactionIndexSeries {
         }
 
        // set initial transaction index as current value, and initial count as 1
-        let mut current_value = transaction_index_vec[0].unwrap();
-        // let mut current_count = 1 as u16;
-        let mut current_count: u32 = 1;
+        // when appending, continue the last run loaded from the archive instead
+        let (mut current_value, mut current_count, skip) = match (self.values.pop(), self.counts.pop()) {
+            (Some(value), Some(count)) => (value, count, 0),
+            _ => (transaction_index_vec[0], 1, 1),
+        };
 
         // iterate through transaction index, skip first, 
-        for transaction_index in transaction_index_vec.iter().skip(1) {
-            let b = transaction_index.unwrap();
+        for transaction_index in transaction_index_vec.iter().skip(skip) {
+            let b = *transaction_index;
             if b == current_value {
-                current_count += 1 as u32;
+                current_count += 1;
             } else {
                 self.values.push(current_value);
-                self.counts.push(current_count.into());
+                self.counts.push(current_count);
 
                 current_value = b;
                 current_count = 1;
             }
         }
         self.values.push(current_value);
-        self.counts.push(current_count.into());
-
-        // check size comparisons
-        let transaction_index_size = transaction_index_vec.capacity() * mem::size_of::<Option<u32>>();
-        let compressed_size = self.values.capacity() * mem::size_of::<u32>() + 
-                            self.counts.capacity() * mem::size_of::<u16>();
-        let compression_ratio = transaction_index_size as f64 / compressed_size as f64;
-
-        // output to terminal
-        println!("[TRANSACTION INDEX MEM] {} → {} bytes ({:.2}x)", transaction_index_size.to_string().red(), compressed_size.to_string().green(), compression_ratio.to_string().bright_blue());
+        self.counts.push(current_count);
 
         // assert that output is equal in len to input
         // assert_eq!()
@@ -65,18 +58,34 @@ impl RLECompressedTransactionIndexSeries {
 
     pub fn create_compressed_df(&mut self, dataset: &DataFrame) -> Result<DataFrame> {
         // call compress function to create value / count references
-        let _compressed_res = self.compress(dataset);
+        self.compress(dataset)?;
         let s1 = Column::new("trans_index_values".into(), &self.values);
         let s2 = Column::new("trans_index_counts".into(), &self.counts);
         let df = DataFrame::new(vec![s1, s2])?;
         Ok(df)
     }
 
-    /// Decompression of RLE compressed block number data in the transfer dataset.
-    pub fn decompress() -> Result<()> {
+    /// Load value / count references from the archive streams, so that a later
+    /// `compress` call continues the encoded runs.
+    pub fn load(&mut self, streams: &Streams) -> Result<()> {
+        self.values = streams.get("trans_index_values")?.u32()?.iter().collect();
+        self.counts = streams.get("trans_index_counts")?.u32()?.into_no_null_iter().collect();
         Ok(())
     }
 
+    /// Decompression of RLE compressed transaction index data in the transfer dataset.
+    pub fn decompress(&mut self, streams: &Streams) -> Result<Column> {
+        self.load(streams)?;
+        streams.check_runs("trans_index_counts", self.values.len(), &self.counts)?;
+
+        // expand each value by its count of consecutive repetitions
+        let mut decompressed: Vec<Option<u32>> = Vec::with_capacity(streams.rows);
+        for (value, count) in self.values.iter().zip(self.counts.iter()) {
+            decompressed.extend(std::iter::repeat_n(*value, *count as usize));
+        }
+        Ok(Column::new("transaction_index".into(), decompressed))
+    }
+
 }
 
 
@@ -0,0 +1,258 @@
+ // Per-token dictionary of frequent amounts for value_string column.
+ // Amounts recurring within one token (airdrops, fixed fees, round sums) are
+ // referenced by position in that token's dictionary, keyed through the erc20
+ // column; the long tail is encoded literally by mantissa / exponent.
+ // Dictionaries may start from the amounts of a trained bundle, in which case
+ // only the amounts following them are stored in the archive.
+
+use std::collections::HashMap;
+use crate::error::{BladeError, Result};
+use polars::prelude::*;
+use super::streams::Streams;
+use super::erc20::decompress_erc20;
+use super::value_string::{check_canonical, factor, MantissaExponentCompressedValueStrings};
+use crate::transfers::writer::stream_cost;
+use crate::transfers::bundle::{BUNDLE_AMOUNTS_STREAM, BUNDLE_SIZES_STREAM, BUNDLE_TOKENS_STREAM};
+
+/// Occurrences of an amount within one token before it enters the dictionary.
+const HOT_MIN_COUNT: usize = 3;
+
+#[derive(Default)]
+pub struct HotDictionaryCompressedValueStrings {
+    pub index: Vec<Option<u32>>,                             // position in the row token's dictionary; None for a literal
+    pub dictionaries: Vec<(Option<String>, Vec<String>)>,    // hot amounts of each token, most frequent first
+    pub literals: MantissaExponentCompressedValueStrings,    // long tail values, in row order
+    pub trained: Vec<usize>,                                 // leading amounts of the leading dictionaries, from the bundle
+}
+
+impl HotDictionaryCompressedValueStrings {
+
+    pub fn new() -> Self {
+        Self {
+            index: Vec::new(),
+            dictionaries: Vec::new(),
+            literals: MantissaExponentCompressedValueStrings::new(),
+            trained: Vec::new(),
+        }
+    }
+
+    /// Start the dictionaries from the hot amounts of a trained bundle; they
+    /// are not stored in the archive.
+    pub fn with_bundle(amounts: &[(Option<String>, Vec<String>)]) -> Self {
+        let mut value_string_compression = Self::new();
+        value_string_compression.dictionaries = amounts.to_vec();
+        value_string_compression.trained = amounts.iter().map(|(_, amounts)| amounts.len()).collect();
+        value_string_compression
+    }
+
+    /// Compress value string column of Transfer dataset against per token
+    /// dictionaries of hot amounts. Loaded dictionaries keep their positions and
+    /// are extended with the hot amounts of `dataset`.
+    pub fn compress(&mut self, dataset: &DataFrame) -> Result<()> {
+
+        // Distill erc20 and value_string columns from dataset
+        let tokens = dataset.column("erc20")?.str()?;
+        let value_strings = dataset.column("value_string")?.str()?;
+
+        // Every amount is checked once, whether it turns out hot or literal
+        for (row, value) in value_strings.iter().enumerate() {
+            if let Some(value) = value {
+                check_canonical(row, value)?;
+            }
+        }
+
+        // Count every amount within its token
+        let mut counts: HashMap<(Option<&str>, &str), usize> = HashMap::new();
+        for (token, value) in tokens.iter().zip(value_strings.iter()) {
+            if let Some(value) = value {
+                *counts.entry((token, value)).or_insert(0) += 1;
+            }
+        }
+
+        // Add new hot amounts, most frequent first so that hot positions stay small
+        let mut positions: HashMap<Option<String>, usize> = self.dictionaries.iter().enumerate()
+            .map(|(position, (token, _))| (token.clone(), position))
+            .collect();
+        let mut lookups: Vec<HashMap<String, u32>> = self.dictionaries.iter()
+            .map(|(_, amounts)| amounts.iter().enumerate().map(|(i, amount)| (amount.clone(), i as u32)).collect())
+            .collect();
+        let mut hot: Vec<((Option<&str>, &str), usize)> = counts.into_iter()
+            .filter(|(_, count)| *count >= HOT_MIN_COUNT)
+            .collect();
+        hot.sort_unstable_by(|(key_a, count_a), (key_b, count_b)| count_b.cmp(count_a).then(key_a.cmp(key_b)));
+        for ((token, value), _) in hot {
+            let token = token.map(str::to_string);
+            let position = *positions.entry(token.clone()).or_insert_with(|| {
+                self.dictionaries.push((token, Vec::new()));
+                lookups.push(HashMap::new());
+                self.dictionaries.len() - 1
+            });
+            let amounts = &mut self.dictionaries[position].1;
+            if !lookups[position].contains_key(value) {
+                lookups[position].insert(value.to_string(), amounts.len() as u32);
+                amounts.push(value.to_string());
+            }
+        }
+
+        // Reference hot amounts, push the rest to the literal streams
+        let token_positions: HashMap<Option<&str>, usize> = self.dictionaries.iter().enumerate()
+            .map(|(position, (token, _))| (token.as_deref(), position))
+            .collect();
+        for (token, value) in tokens.iter().zip(value_strings.iter()) {
+            let hot = match (value, token_positions.get(&token)) {
+                (Some(value), Some(position)) => lookups[*position].get(value).copied(),
+                _ => None,
+            };
+            self.index.push(hot);
+            if hot.is_none() {
+                self.literals.push_canonical(value);
+            }
+        }
+
+        Ok(())
+    }
+
+
+    /// Compressed streams of `dataset`. A new archive keeps the dictionaries only
+    /// when they write smaller than literals alone, returning just the two
+    /// mantissa / exponent dataframes otherwise; loaded dictionaries are continued.
+    pub fn create_compressed_df(&mut self, dataset: &DataFrame) -> Result<Vec<DataFrame>> {
+        // call compress function to create dictionary / literal references
+        let fresh = self.index.is_empty();
+        self.compress(dataset)?;
+        let dataframes = self.dataframes()?;
+        if fresh {
+            let literal_dataframes = self.literals_only(dataset)?.dataframes()?;
+            if stream_cost(&literal_dataframes)? <= stream_cost(&dataframes)? {
+                return Ok(literal_dataframes);
+            }
+        }
+        Ok(dataframes)
+    }
+
+    /// The values compressed so far as literals alone, for a new archive to
+    /// compare against: hot amounts are factored once per dictionary entry and
+    /// placed between the already factored literals in row order.
+    fn literals_only(&self, dataset: &DataFrame) -> Result<MantissaExponentCompressedValueStrings> {
+        let tokens = dataset.column("erc20")?.str()?;
+        let token_positions: HashMap<Option<&str>, usize> = self.dictionaries.iter().enumerate()
+            .map(|(position, (token, _))| (token.as_deref(), position))
+            .collect();
+        let factored: Vec<Vec<(u32, std::result::Result<u64, &str>)>> = self.dictionaries.iter()
+            .map(|(_, amounts)| amounts.iter().map(|amount| factor(amount)).collect())
+            .collect();
+
+        let shorter = || BladeError::Integrity("value literals are shorter than their references".to_string());
+        let mut literal_rows = self.literals.exponents.iter().zip(self.literals.mantissas.iter());
+        let mut wide_mantissas = self.literals.wide_mantissas.iter();
+        let mut literals = MantissaExponentCompressedValueStrings::new();
+        for (token, index) in tokens.iter().zip(self.index.iter()) {
+            match index {
+                Some(index) => {
+                    let (exponent, mantissa) = token_positions.get(&token)
+                        .and_then(|position| factored[*position].get(*index as usize))
+                        .ok_or_else(|| BladeError::Integrity(format!("value_hot_index {} is not in the dictionary of token {:?}", index, token)))?;
+                    literals.push_factored(*exponent, *mantissa);
+                },
+                None => match literal_rows.next().ok_or_else(shorter)? {
+                    (None, _) => literals.push_canonical(None),
+                    (Some(exponent), Some(mantissa)) => literals.push_factored(*exponent, Ok(*mantissa)),
+                    (Some(exponent), None) => literals.push_factored(*exponent, Err(wide_mantissas.next().ok_or_else(shorter)?)),
+                },
+            }
+        }
+        Ok(literals)
+    }
+
+    /// Reference, dictionary and literal streams of the values compressed so far.
+    /// Dictionaries hold only the amounts following the trained ones.
+    pub fn dataframes(&self) -> Result<Vec<DataFrame>> {
+        let stored: Vec<(Option<&str>, &[String])> = self.dictionaries.iter().enumerate()
+            .map(|(position, (token, amounts))| (token.as_deref(), &amounts[self.trained.get(position).copied().unwrap_or(0)..]))
+            .filter(|(_, amounts)| !amounts.is_empty())
+            .collect();
+        let tokens: Vec<Option<&str>> = stored.iter().map(|(token, _)| *token).collect();
+        let sizes: Vec<u32> = stored.iter().map(|(_, amounts)| amounts.len() as u32).collect();
+        let amounts: Vec<&str> = stored.iter().flat_map(|(_, amounts)| amounts.iter().map(String::as_str)).collect();
+
+        let s1 = Column::new("value_hot_index".into(), &self.index);
+        let s2 = Column::new("value_dictionary_tokens".into(), tokens);
+        let s3 = Column::new("value_dictionary_sizes".into(), sizes);
+        let s4 = Column::new("value_dictionary_amounts".into(), amounts);
+        let mut dataframes = vec![DataFrame::new(vec![s1])?, DataFrame::new(vec![s2, s3])?, DataFrame::new(vec![s4])?];
+        dataframes.extend(self.literals.dataframes()?);
+        Ok(dataframes)
+    }
+
+    /// Load references, dictionaries and literals from the archive streams, so
+    /// that a later `compress` call extends them. Archives written with a
+    /// bundle start each dictionary from the bundle's amounts.
+    pub fn load(&mut self, streams: &Streams) -> Result<()> {
+        self.index = streams.get("value_hot_index")?.u32()?.iter().collect();
+        self.dictionaries.clear();
+        self.trained.clear();
+        if streams.contains(BUNDLE_TOKENS_STREAM) {
+            self.dictionaries = read_dictionaries(streams, BUNDLE_TOKENS_STREAM, BUNDLE_SIZES_STREAM, BUNDLE_AMOUNTS_STREAM)?;
+            self.trained = self.dictionaries.iter().map(|(_, amounts)| amounts.len()).collect();
+        }
+
+        let mut positions: HashMap<Option<String>, usize> = self.dictionaries.iter().enumerate()
+            .map(|(position, (token, _))| (token.clone(), position))
+            .collect();
+        for (token, amounts) in read_dictionaries(streams, "value_dictionary_tokens", "value_dictionary_sizes", "value_dictionary_amounts")? {
+            match positions.get(&token) {
+                Some(position) => self.dictionaries[*position].1.extend(amounts),
+                None => {
+                    positions.insert(token.clone(), self.dictionaries.len());
+                    self.dictionaries.push((token, amounts));
+                },
+            }
+        }
+        self.literals.load(streams)
+    }
+
+    /// Decompression of dictionary / literal compressed value strings; the
+    /// erc20 column is decoded first to pick each row's dictionary.
+    pub fn decompress(&mut self, streams: &Streams) -> Result<Column> {
+        self.load(streams)?;
+        let tokens = decompress_erc20(streams)?;
+        let tokens = tokens.str()?;
+
+        let token_positions: HashMap<Option<&str>, usize> = self.dictionaries.iter().enumerate()
+            .map(|(position, (token, _))| (token.as_deref(), position))
+            .collect();
+        let mut literals = self.literals.values()?.into_iter();
+        let mut decompressed: Vec<Option<String>> = Vec::with_capacity(streams.rows);
+        for (row, (token, index)) in tokens.iter().zip(self.index.iter()).enumerate() {
+            let value = match index {
+                Some(index) => {
+                    let amount = token_positions.get(&token)
+                        .and_then(|position| self.dictionaries[*position].1.get(*index as usize))
+                        .ok_or_else(|| BladeError::Integrity(format!("value_hot_index {} at row {} is not in the dictionary of token {:?}", index, row, token)))?;
+                    Some(amount.clone())
+                },
+                None => literals.next()
+                    .ok_or_else(|| BladeError::Integrity("value literals are shorter than their references".to_string()))?,
+            };
+            decompressed.push(value);
+        }
+        Ok(Column::new("value_string".into(), decompressed))
+    }
+}
+
+/// Per token dictionaries from token / size / amount streams.
+fn read_dictionaries(streams: &Streams, tokens: &str, sizes: &str, amounts: &str) -> Result<Vec<(Option<String>, Vec<String>)>> {
+    let tokens = streams.get(tokens)?.str()?;
+    let sizes = streams.get(sizes)?.u32()?;
+    let mut amounts = streams.get(amounts)?.str()?.into_no_null_iter();
+
+    let mut dictionaries = Vec::new();
+    for (token, size) in tokens.iter().zip(sizes.into_no_null_iter()) {
+        let token_amounts: Vec<String> = amounts.by_ref().take(size as usize).map(str::to_string).collect();
+        if token_amounts.len() != size as usize {
+            return Err(BladeError::Integrity("dictionary amounts are shorter than their sizes".to_string()));
+        }
+        dictionaries.push((token.map(str::to_string), token_amounts));
+    }
+    Ok(dictionaries)
+}
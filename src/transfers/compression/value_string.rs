@@ -1,104 +1,142 @@
- // Normalization compression for value_string column.
- // String --> BigDecimal --> f64 --> normalized vector.
+ // Mantissa / exponent decomposition for value_string column.
+ // Decimal string --> significant integer (mantissa) * 10^exponent, lossless.
+ // Exponents follow token decimals and repeat heavily, so they are kept apart
+ // from the mantissas; mantissas overflowing u64 go to a stream of their own.
 
-use std::mem;
-use anyhow::Result;
-use std::str::FromStr;
+use crate::error::{BladeError, Result};
 use polars::prelude::*;
-use owo_colors::OwoColorize;
-use bigdecimal::BigDecimal;
-use bigdecimal::ToPrimitive;
-use float_eq::assert_float_eq;
-
-pub struct NormalizedCompressedValueStrings {
-    index: Vec<u32>,
-    normalized_vs_vec: Vec<f64>,
+use super::streams::Streams;
+
+#[derive(Default)]
+pub struct MantissaExponentCompressedValueStrings {
+    pub exponents: Vec<Option<u32>>,    // trailing zeros of each value; None for a null value
+    pub mantissas: Vec<Option<u64>>,    // value without trailing zeros; None when null or wide
+    pub wide_mantissas: Vec<String>,    // mantissas overflowing u64, in row order
 }
 
-impl NormalizedCompressedValueStrings {
+impl MantissaExponentCompressedValueStrings {
 
     pub fn new() -> Self {
         Self {
-            index: Vec::new(),
-            normalized_vs_vec: Vec::new(),
+            exponents: Vec::new(),
+            mantissas: Vec::new(),
+            wide_mantissas: Vec::new(),
+        }
+    }
+
+    /// Compress value string column of Transfer dataset by factoring every amount
+    /// into a mantissa and a power of ten exponent.
+    pub fn compress(&mut self, dataset: &DataFrame) -> Result<()> {
+
+        // Distill value_string column from dataset
+        let value_strings: &Column = dataset.column("value_string")?;
+        let value_strings_series = value_strings.str()?;
+
+        for (row, item) in value_strings_series.iter().enumerate() {
+            self.push(row, item)?;
         }
+
+        Ok(())
     }
 
-    /// Compress value string column of Transfer dataset through vector normalization.
-    pub fn compress(&mut self, dataset: &DataFrame) -> Result<(Vec<u32>, Vec<f64>)> {
-
-        // Distill value_string column from dataset and unwrap the str's
-        let value_strings: &Column = dataset.column("value_string").unwrap();
-        let value_strings_series = value_strings.str().unwrap();
-
-        // Parse to BigDecimal, set scaled value for easier reference, push to vec
-        let mut result: Vec<BigDecimal> = Vec::new();
-        for val in value_strings_series.iter() {
-            let decimal = BigDecimal::from_str(val.unwrap()).unwrap();
-            // NOTE: check if scale of 10 is proper
-            let divisor = BigDecimal::from(10);
-            let scaled_value = decimal / divisor;
-            result.push(scaled_value);
+
+    /// Factor one value (`row` of the input dataset) into the streams.
+    pub fn push(&mut self, row: usize, item: Option<&str>) -> Result<()> {
+        if let Some(value) = item {
+            check_canonical(row, value)?;
         }
+        self.push_canonical(item);
+        Ok(())
+    }
 
-        // Iterate through result, convert values to f64
-        let result_f64: Vec<f64> = result.iter().map(|x: &BigDecimal| x.to_f64().unwrap_or(0.0)).collect();
-        // Iterate; sum of squares
-        let squared_sum: f64 = result_f64.iter().map(|x: &f64| x * x).sum();
-        // Square root of sum of squares
-        let sqrt_of_sum: f64 = squared_sum.sqrt();
-        // Normalize vec 
-        let normalized_vec: Vec<f64> = result_f64.iter().map(|x: &f64| x / sqrt_of_sum).collect();
-        // Calculate sum of squares for assertion check
-        let sum_of_squares: f64 = normalized_vec.iter().map(|x: &f64| x * x).sum();
-        
-        // NOTE: Optionally, assert equivalence between normalized vec and result vec
-        // assert_eq!(normalized_vec.len(), result.len());
-
-        // Assert that the sum of the squares is approximately 1.0
-        // to verify normalization accuracy.
-        assert_float_eq!(sum_of_squares, 1.0, abs <= 1e-10);
-
-        // Calculate size of original string 
-        let original_str_len = value_strings_series.iter()
-            .map(|s| s.unwrap().len())
-            .sum::<usize>();
-
-        // Calculate size of compresed vec
-        let compressed_size = normalized_vec.capacity() * mem::size_of::<u16>();
-        let compression_ratio = original_str_len as f64 / compressed_size as f64;
-
-        // // Print comparisons to terminal
-        println!("[VALUE STRINGS MEM] {} → {} bytes ({:.2}x)", original_str_len.to_string().red(), compressed_size.to_string().green(), compression_ratio.to_string().bright_blue());
-
-        // Get index len and set values to return
-        self.normalized_vs_vec.extend(normalized_vec);
-        self.index = (1..=self.normalized_vs_vec.len())
-            .map(|i| i as u32)
-            .collect();
-
-        Ok((self.index.clone(), self.normalized_vs_vec.clone()))
+    /// Factor one value already checked by `check_canonical` into the streams.
+    pub fn push_canonical(&mut self, item: Option<&str>) {
+        match item.map(factor) {
+            None => {
+                self.exponents.push(None);
+                self.mantissas.push(None);
+            },
+            Some((exponent, mantissa)) => self.push_factored(exponent, mantissa),
+        }
+    }
 
+    /// Push a value factored by `factor`.
+    pub fn push_factored(&mut self, exponent: u32, mantissa: std::result::Result<u64, &str>) {
+        self.exponents.push(Some(exponent));
+        match mantissa {
+            Ok(mantissa) => self.mantissas.push(Some(mantissa)),
+            Err(digits) => {
+                self.mantissas.push(None);
+                self.wide_mantissas.push(digits.to_string());
+            },
+        }
     }
 
-    
-    pub fn create_compressed_df(&mut self, dataset: &DataFrame) -> Result<DataFrame> {
-        // call compress function to create value / count references
-        let _compressed_res = self.compress(dataset);
-        let s1 = Column::new("value_string_index".into(), &self.index);
-        let s2 = Column::new("value_string_normalized".into(), &self.normalized_vs_vec);
-        let df = DataFrame::new(vec![s1, s2])?;
-        Ok(df)
+    pub fn create_compressed_df(&mut self, dataset: &DataFrame) -> Result<Vec<DataFrame>> {
+        // call compress function to create exponent / mantissa references
+        self.compress(dataset)?;
+        self.dataframes()
     }
 
+    /// Exponent / mantissa streams of the values pushed so far.
+    pub fn dataframes(&self) -> Result<Vec<DataFrame>> {
+        let s1 = Column::new("value_exponents".into(), &self.exponents);
+        let s2 = Column::new("value_mantissas".into(), &self.mantissas);
+        let s3 = Column::new("value_wide_mantissas".into(), &self.wide_mantissas);
+        Ok(vec![DataFrame::new(vec![s1, s2])?, DataFrame::new(vec![s3])?])
+    }
 
+    /// Load exponents and mantissas from the archive streams, so that a later
+    /// `compress` call appends after the archived rows.
+    pub fn load(&mut self, streams: &Streams) -> Result<()> {
+        self.exponents = streams.get("value_exponents")?.u32()?.iter().collect();
+        self.mantissas = streams.get("value_mantissas")?.u64()?.iter().collect();
+        self.wide_mantissas = streams.get("value_wide_mantissas")?.str()?.into_no_null_iter().map(str::to_string).collect();
+        Ok(())
+    }
 
-    // TODO: decompress
-//     pub fn decompress_value_string(&mut self, dataset: &DataFrame) -> Result<(), Box<dyn std::error::Error>> {
-// 
-//         Ok(())
-//     }
+    /// Decompression of mantissa / exponent compressed value strings.
+    pub fn decompress(&mut self, streams: &Streams) -> Result<Column> {
+        self.load(streams)?;
+        Ok(Column::new("value_string".into(), self.values()?))
+    }
 
+    /// Rebuild the decimal strings from the loaded streams.
+    pub fn values(&self) -> Result<Vec<Option<String>>> {
+        // rows without a u64 mantissa take the next wide mantissa
+        let mut wide_mantissas = self.wide_mantissas.iter();
+        let mut decompressed: Vec<Option<String>> = Vec::with_capacity(self.exponents.len());
+        for (exponent, mantissa) in self.exponents.iter().zip(self.mantissas.iter()) {
+            let value = match (exponent, mantissa) {
+                (None, _) => None,
+                (Some(exponent), Some(mantissa)) => Some(format!("{}{}", mantissa, "0".repeat(*exponent as usize))),
+                (Some(exponent), None) => {
+                    let mantissa = wide_mantissas.next()
+                        .ok_or_else(|| BladeError::Integrity("value_wide_mantissas is shorter than its references".to_string()))?;
+                    Some(format!("{}{}", mantissa, "0".repeat(*exponent as usize)))
+                },
+            };
+            decompressed.push(value);
+        }
+        Ok(decompressed)
+    }
+}
 
+/// Only canonical unsigned integers are restored exactly; error on anything else.
+pub fn check_canonical(row: usize, value: &str) -> Result<()> {
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) || (value.len() > 1 && value.starts_with('0')) {
+        return Err(BladeError::malformed("value_string", row, format!("`{}` is not a canonical unsigned integer", value)));
+    }
+    Ok(())
 }
 
+/// Exponent (trailing zeros) and mantissa of a canonical value; mantissas
+/// overflowing u64 are returned as their digits. Zero itself is 0 * 10^0.
+pub fn factor(value: &str) -> (u32, std::result::Result<u64, &str>) {
+    let digits = value.trim_end_matches('0');
+    let (digits, exponent) = match digits.is_empty() {
+        true => ("0", 0),
+        false => (digits, value.len() - digits.len()),
+    };
+    (exponent as u32, digits.parse::<u64>().map_err(|_| digits))
+}
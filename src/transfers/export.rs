@@ -0,0 +1,184 @@
+// Export of decoded transfers to other file formats.
+// Decompressed transfers are written as parquet (with a chosen compression),
+// CSV, NDJSON or Arrow IPC / Feather, optionally projected to a subset of
+// columns. The value columns archives drop are derived again when exported.
+
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+use polars::prelude::*;
+use crate::error::{BladeError, Result};
+use super::record::with_derived_values;
+
+/// Columns archives do not store, derived from value_string when decoding.
+pub const DERIVED_COLUMNS: [&str; 2] = ["value_binary", "value_f64"];
+
+/// File formats decoded transfers are exported to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    #[default]
+    Parquet,
+    Csv,
+    Ndjson,
+    Ipc,        // Arrow IPC file, aka Feather v2
+}
+
+impl ExportFormat {
+
+    /// Format of `filepath` by extension: `.parquet`, `.csv`, `.ndjson` / `.jsonl`
+    /// or `.arrow` / `.ipc` / `.feather`; `None` for any other extension.
+    pub fn from_path(filepath: &Path) -> Option<Self> {
+        match filepath.extension()?.to_str()? {
+            "parquet" => Some(ExportFormat::Parquet),
+            "csv" => Some(ExportFormat::Csv),
+            "ndjson" | "jsonl" => Some(ExportFormat::Ndjson),
+            "arrow" | "ipc" | "feather" => Some(ExportFormat::Ipc),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = BladeError;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format {
+            "parquet" => Ok(ExportFormat::Parquet),
+            "csv" => Ok(ExportFormat::Csv),
+            "ndjson" | "jsonl" => Ok(ExportFormat::Ndjson),
+            "ipc" | "arrow" | "feather" => Ok(ExportFormat::Ipc),
+            _ => Err(BladeError::Format(format!("unknown export format `{}`, expected parquet, csv, ndjson or ipc", format))),
+        }
+    }
+}
+
+/// Compression of an exported parquet or IPC file, with an optional level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportCompression {
+    Uncompressed,
+    Snappy,
+    Gzip(Option<u8>),
+    Brotli(Option<u32>),
+    Zstd(Option<i32>),
+    Lz4,
+}
+
+impl ExportCompression {
+
+    fn parquet(&self) -> Result<ParquetCompression> {
+        Ok(match *self {
+            ExportCompression::Uncompressed => ParquetCompression::Uncompressed,
+            ExportCompression::Snappy => ParquetCompression::Snappy,
+            ExportCompression::Gzip(level) => ParquetCompression::Gzip(level.map(GzipLevel::try_new).transpose()?),
+            ExportCompression::Brotli(level) => ParquetCompression::Brotli(level.map(BrotliLevel::try_new).transpose()?),
+            ExportCompression::Zstd(level) => ParquetCompression::Zstd(level.map(ZstdLevel::try_new).transpose()?),
+            ExportCompression::Lz4 => ParquetCompression::Lz4Raw,
+        })
+    }
+
+    fn ipc(&self) -> Result<Option<IpcCompression>> {
+        match self {
+            ExportCompression::Uncompressed => Ok(None),
+            ExportCompression::Lz4 => Ok(Some(IpcCompression::LZ4)),
+            ExportCompression::Zstd(None) => Ok(Some(IpcCompression::ZSTD)),
+            other => Err(BladeError::Format(format!("IPC files are compressed with lz4 or zstd (without level), not {:?}", other))),
+        }
+    }
+}
+
+impl FromStr for ExportCompression {
+    type Err = BladeError;
+
+    /// `<codec>` or `<codec>:<level>`, eg: `zstd:9`.
+    fn from_str(compression: &str) -> Result<Self> {
+        let (codec, level) = match compression.split_once(':') {
+            Some((codec, level)) => (codec, Some(level)),
+            None => (compression, None),
+        };
+        let invalid = || BladeError::Format(format!("invalid compression level in `{}`", compression));
+        let no_level = |parsed: ExportCompression| match level {
+            Some(_) => Err(BladeError::Format(format!("{} takes no compression level", codec))),
+            None => Ok(parsed),
+        };
+        match codec {
+            "uncompressed" => no_level(ExportCompression::Uncompressed),
+            "snappy" => no_level(ExportCompression::Snappy),
+            "lz4" => no_level(ExportCompression::Lz4),
+            "gzip" => Ok(ExportCompression::Gzip(level.map(|level| level.parse().map_err(|_| invalid())).transpose()?)),
+            "brotli" => Ok(ExportCompression::Brotli(level.map(|level| level.parse().map_err(|_| invalid())).transpose()?)),
+            "zstd" => Ok(ExportCompression::Zstd(level.map(|level| level.parse().map_err(|_| invalid())).transpose()?)),
+            _ => Err(BladeError::Format(format!("unknown compression `{}`, expected uncompressed, snappy, gzip, brotli, zstd or lz4", codec))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    pub format: Option<ExportFormat>,               // file format written; parquet when None
+    pub compression: Option<ExportCompression>,     // parquet / IPC compression; the writer's default when None
+    pub columns: Option<Vec<String>>,               // columns exported, in this order; all when None
+}
+
+impl ExportOptions {
+
+    pub fn new() -> Self {
+        Self {
+            format: None,
+            compression: None,
+            columns: None,
+        }
+    }
+
+    /// Stored columns to decode for the export: the projection, with derived
+    /// value columns read from value_string. `None` decodes every column.
+    pub fn stored_columns(&self) -> Option<Vec<String>> {
+        self.columns.as_deref().map(stored_columns)
+    }
+}
+
+/// Stored columns `columns` decode from: derived value columns are read from
+/// value_string, each stored column is listed once.
+pub fn stored_columns(columns: &[String]) -> Vec<String> {
+    let mut stored: Vec<String> = Vec::new();
+    for column in columns {
+        let column = match DERIVED_COLUMNS.contains(&column.as_str()) {
+            true => "value_string".to_string(),
+            false => column.clone(),
+        };
+        if !stored.contains(&column) {
+            stored.push(column);
+        }
+    }
+    stored
+}
+
+/// Write decoded transfers into `sink` in the export format: derived value
+/// columns are added back, then the projection applied.
+pub fn export_transfers<W: Write>(sink: W, df: &DataFrame, options: &ExportOptions) -> Result<()> {
+    let mut df = with_derived_values(df)?;
+    if let Some(columns) = &options.columns {
+        for column in columns {
+            if df.column(column).is_err() {
+                return Err(BladeError::Schema(format!("column `{}` is not a transfers column", column)));
+            }
+        }
+        df = df.select(columns.iter().map(String::as_str))?;
+    }
+
+    match (options.format.unwrap_or_default(), options.compression) {
+        (ExportFormat::Parquet, compression) => {
+            let compression = compression.map(|compression| compression.parquet()).transpose()?.unwrap_or_default();
+            ParquetWriter::new(sink).with_compression(compression).finish(&mut df)?;
+        },
+        (ExportFormat::Ipc, compression) => {
+            let compression = compression.map(|compression| compression.ipc()).transpose()?.flatten();
+            IpcWriter::new(sink).with_compression(compression).finish(&mut df)?;
+        },
+        (format, Some(compression)) => {
+            return Err(BladeError::Format(format!("{:?} exports are not compressed, got {:?}", format, compression)));
+        },
+        (ExportFormat::Csv, None) => CsvWriter::new(sink).finish(&mut df)?,
+        (ExportFormat::Ndjson, None) => JsonWriter::new(sink).with_json_format(JsonFormat::JsonLines).finish(&mut df)?,
+    }
+    Ok(())
+}
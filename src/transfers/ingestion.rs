@@ -1,6 +1,12 @@
-use std::path::PathBuf;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use ethnum::U256;
 use polars::prelude::*;
+use polars::io::mmap::MmapBytesReader;
+use crate::error::{BladeError, Result};
+use super::record::with_derived_values;
 
+#[derive(Default)]
 pub struct TransferIngestion {
     pub reference_df: DataFrame,
 }
@@ -14,7 +20,7 @@ impl TransferIngestion {
     }
 
     /// Generate default `transfer` schema to reference input validity
-    pub fn _generate_reference(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn _generate_reference(&mut self) -> Result<()> {
         let df: DataFrame = df!(
             "block_number" => [0_u32],
             "transaction_index" => [0_u32],
@@ -27,30 +33,205 @@ impl TransferIngestion {
             "value_string" => [""],
             "value_f64" => [0.0],
             "chain_id" => [0_u64],
-        )
-        .unwrap();
+        )?;
         self.reference_df = df;
         Ok(())
     }
 
     /// Check input validity of parquet file against default schema
-    pub fn check_schema_validity(&mut self, filepath: &PathBuf) -> PolarsResult<DataFrame> {
+    pub fn check_schema_validity(&mut self, filepath: &PathBuf) -> Result<DataFrame> {
         // Generate reference dataframe to check incoming schema
-        let _ = self._generate_reference();
+        self._generate_reference()?;
         
-        // Open file path and attempt to turn into dataframe
-        let mut file = std::fs::File::open(filepath).unwrap();
-        let df = ParquetReader::new(&mut file).finish()?;
+        // Open file path and attempt to turn into dataframe; exports are mapped to the transfers schema
+        let df = read_transfers_file(filepath)?;
 
         // Get schema from incoming dataset and reference schema
         let sch = df.schema();
         let ref_sch = self.reference_df.schema();
 
-        // Assert that schemas match
-        assert_eq!(sch, ref_sch, "Schema mismatch for file {:?}: expected reference schema", filepath);
+        // Check that schemas match
+        if sch != ref_sch {
+            return Err(BladeError::Schema(format!("schema mismatch for file {:?}: expected {:?}, got {:?}", filepath, ref_sch, sch)));
+        }
         Ok(df)
     } 
 
+    /// Check input validity of an in-memory dataframe against default schema
+    pub fn check_dataframe_validity(&mut self, df: &DataFrame) -> Result<()> {
+        // Generate reference dataframe to check incoming schema
+        self._generate_reference()?;
+
+        // Get schema from incoming dataset and reference schema
+        let sch = df.schema();
+        let ref_sch = self.reference_df.schema();
+
+        if sch != ref_sch {
+            return Err(BladeError::Schema(format!("expected reference transfers schema {:?}, got {:?}", ref_sch, sch)));
+        }
+        Ok(())
+    }
+
+}
+
+
+/// Formats transfers are ingested from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    Parquet,
+    Csv,
+    Ndjson,     // one JSON object per line
+    Json,       // one JSON array of objects
+}
+
+impl InputFormat {
+
+    /// Format of `filepath` by extension: `.parquet`, `.csv`, `.ndjson` / `.jsonl`
+    /// or `.json`; `None` for any other extension.
+    pub fn from_path(filepath: &Path) -> Option<Self> {
+        match filepath.extension()?.to_str()? {
+            "parquet" => Some(InputFormat::Parquet),
+            "csv" => Some(InputFormat::Csv),
+            "ndjson" | "jsonl" => Some(InputFormat::Ndjson),
+            "json" => Some(InputFormat::Json),
+            _ => None,
+        }
+    }
+
+    /// Format of transfers read from a stream, by content: the parquet magic,
+    /// a JSON array, JSON lines, or CSV for anything else.
+    pub fn sniff(bytes: &[u8]) -> Self {
+        if bytes.starts_with(b"PAR1") {
+            return InputFormat::Parquet;
+        }
+        match bytes.iter().find(|byte| !byte.is_ascii_whitespace()) {
+            Some(b'[') => InputFormat::Json,
+            Some(b'{') => InputFormat::Ndjson,
+            _ => InputFormat::Csv,
+        }
+    }
 }
 
+/// Read a transfers file. Parquet is taken as is (also without a known
+/// extension); CSV and JSON exports are mapped into the transfers schema.
+pub fn read_transfers_file(filepath: &Path) -> Result<DataFrame> {
+    let file = File::open(filepath)?;
+    read_transfers(file, InputFormat::from_path(filepath).unwrap_or(InputFormat::Parquet))
+}
 
+/// Read transfers in `format` from `source`, mapped as `read_transfers_file` does.
+pub fn read_transfers<R: MmapBytesReader>(source: R, format: InputFormat) -> Result<DataFrame> {
+    match format {
+        InputFormat::Parquet => Ok(ParquetReader::new(source).finish()?),
+        // every CSV field read as text, so that amounts and hex keep all their digits
+        InputFormat::Csv => canonical_transfers(&CsvReadOptions::default()
+            .with_infer_schema_length(Some(0))
+            .into_reader_with_file_handle(source)
+            .finish()?),
+        InputFormat::Ndjson => canonical_transfers(&JsonLineReader::new(source).finish()?),
+        InputFormat::Json => canonical_transfers(&JsonReader::new(source).finish()?),
+    }
+}
+
+/// Map an exported transfers table into the transfers schema. Whatever their
+/// exported type, values are read as text: integers may be decimal or 0x hex,
+/// hashes and addresses become lowercase 0x hex, and amounts may be decimal or 0x
+/// hex. value_binary / value_f64 are derived from the amount, taken from
+/// value_string or else value_binary.
+pub fn canonical_transfers(export: &DataFrame) -> Result<DataFrame> {
+    let text = |name: &str| -> Result<Option<StringChunked>> {
+        match export.column(name) {
+            Ok(column) => Ok(Some(column.cast(&DataType::String)?.str()?.clone())),
+            Err(_) => Ok(None),
+        }
+    };
+    let required = |name: &str| -> Result<StringChunked> {
+        text(name)?.ok_or_else(|| BladeError::Schema(format!("transfers export has no `{}` column", name)))
+    };
+
+    let mut columns: Vec<Column> = Vec::new();
+    for name in ["block_number", "transaction_index", "log_index"] {
+        let values = required(name)?.iter().enumerate()
+            .map(|(row, value)| value.map(|value| parse_integer(name, row, value)
+                .and_then(|integer| u32::try_from(integer).map_err(|_| BladeError::malformed(name, row, format!("`{}` does not fit a u32", value))))).transpose())
+            .collect::<Result<Vec<Option<u32>>>>()?;
+        columns.push(Column::new(name.into(), values));
+    }
+    for name in ["transaction_hash", "erc20", "from_address", "to_address"] {
+        let values = required(name)?.iter().enumerate()
+            .map(|(row, value)| value.map(|value| parse_hex(name, row, value)).transpose())
+            .collect::<Result<Vec<Option<String>>>>()?;
+        columns.push(Column::new(name.into(), values));
+    }
+
+    // amount: value_string, decimal or hex, else the 32 byte value_binary
+    let (name, amounts) = match text("value_string")? {
+        Some(amounts) => ("value_string", amounts),
+        None => ("value_binary", required("value_binary")?),
+    };
+    let values = amounts.iter().enumerate()
+        .map(|(row, value)| value.map(|value| parse_amount(name, row, value).map(|amount| amount.to_string())).transpose())
+        .collect::<Result<Vec<Option<String>>>>()?;
+    columns.push(Column::new("value_string".into(), values));
+
+    let values = required("chain_id")?.iter().enumerate()
+        .map(|(row, value)| value.map(|value| parse_integer("chain_id", row, value)).transpose())
+        .collect::<Result<Vec<Option<u64>>>>()?;
+    columns.push(Column::new("chain_id".into(), values));
+
+    with_derived_values(&DataFrame::new(columns)?)
+}
+
+/// Decimal or 0x hex integer.
+fn parse_integer(column: &str, row: usize, value: &str) -> Result<u64> {
+    let parsed = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|e| BladeError::malformed(column, row, format!("`{}` is not an integer: {}", value, e)))
+}
+
+/// Hex with or without 0x prefix, as lowercase 0x hex.
+fn parse_hex(column: &str, row: usize, value: &str) -> Result<String> {
+    let hex = value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")).unwrap_or(value);
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(BladeError::malformed(column, row, format!("`{}` is not hex", value)));
+    }
+    Ok(format!("0x{}", hex.to_ascii_lowercase()))
+}
+
+/// Decimal or 0x hex amount of up to 256 bits.
+fn parse_amount(column: &str, row: usize, value: &str) -> Result<U256> {
+    let parsed = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => U256::from_str_radix(hex, 16),
+        None => U256::from_str_radix(value, 10),
+    };
+    parsed.map_err(|e| BladeError::malformed(column, row, format!("`{}` is not a u256 amount: {}", value, e)))
+}
+
+/// Naming parts of a cryo output file, eg:
+/// `ethereum__erc20_transfers__10001000_to_10001999.parquet`
+pub struct CryoFilename {
+    pub chain: String,
+    pub dataset: String,
+    pub start_block: u32,
+    pub end_block: u32,
+}
+
+/// Parse a cryo style filename (optionally prefixed with `BLADE_`); `None` when
+/// the name does not follow the `{chain}__{dataset}__{start}_to_{end}` layout.
+pub fn parse_cryo_filename(filepath: &Path) -> Option<CryoFilename> {
+    let stem = filepath.file_stem()?.to_str()?;
+    let stem = stem.strip_prefix("BLADE_").unwrap_or(stem);
+    let parts: Vec<&str> = stem.split("__").collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    let (start, end) = parts[2].split_once("_to_")?;
+    Some(CryoFilename {
+        chain: parts[0].to_string(),
+        dataset: parts[1].to_string(),
+        start_block: start.parse().ok()?,
+        end_block: end.parse().ok()?,
+    })
+}
@@ -0,0 +1,105 @@
+// Integrity checks for BLADE archives.
+// Every encoded stream and the manifest carry a CRC32 checksum, computed when
+// the archive is written and verified whenever it is read back.
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use crate::error::{BladeError, Result};
+use crc32fast::Hasher;
+use polars::prelude::*;
+use owo_colors::OwoColorize;
+use super::manifest::MANIFEST_COLUMN;
+use super::reader::parquet_reader;
+
+
+/// CRC32 over the values of a stream. Each value is hashed with a null marker
+/// and its length, so shifted or nulled values change the checksum.
+pub fn stream_checksum(column: &Column) -> Result<u32> {
+    let mut hasher = Hasher::new();
+    macro_rules! hash_numeric {
+        ($values:expr) => {
+            for value in $values.iter() {
+                hash_value(&mut hasher, value.map(|v| v.to_le_bytes()).as_ref().map(|b| b.as_slice()));
+            }
+        };
+    }
+    let series = column.as_materialized_series();
+    match series.dtype() {
+        DataType::UInt32 => hash_numeric!(series.u32()?),
+        DataType::UInt64 => hash_numeric!(series.u64()?),
+        DataType::Int64 => hash_numeric!(series.i64()?),
+        DataType::Float64 => hash_numeric!(series.f64()?),
+        DataType::String => series.str()?.iter().for_each(|v| hash_value(&mut hasher, v.map(str::as_bytes))),
+        DataType::Binary => series.binary()?.iter().for_each(|v| hash_value(&mut hasher, v)),
+        other => return Err(BladeError::Format(format!("cannot checksum stream `{}` of type {}", column.name(), other))),
+    }
+    Ok(hasher.finalize())
+}
+
+/// CRC32 of the serialized manifest.
+pub fn manifest_checksum(json: &str) -> u32 {
+    crc32fast::hash(json.as_bytes())
+}
+
+fn hash_value(hasher: &mut Hasher, value: Option<&[u8]>) {
+    match value {
+        Some(bytes) => {
+            hasher.update(&[1]);
+            hasher.update(&(bytes.len() as u32).to_le_bytes());
+            hasher.update(bytes);
+        },
+        None => hasher.update(&[0]),
+    }
+}
+
+/// Scan a directory tree (eg: a `--mirror` output) for BLADE archives and
+/// verify each of them, returning the damaged archives along with the reason
+/// they failed verification; an archive that cannot be opened or parsed at all
+/// is damaged as well. Progress is logged to stderr.
+pub fn fsck_directory(directory: &Path) -> Result<Vec<(PathBuf, String)>> {
+    let mut filepaths: Vec<PathBuf> = Vec::new();
+    collect_archives(directory, &mut filepaths)?;
+    filepaths.sort();
+
+    let mut damaged: Vec<(PathBuf, String)> = Vec::new();
+    for filepath in filepaths {
+        // parquet_reader checks the manifest and every stream checksum
+        match parquet_reader(&filepath) {
+            Ok(_) => eprintln!("{} {:?}", "[OK]".green(), filepath),
+            Err(e) => {
+                eprintln!("{} {:?}: {}", "[DAMAGED]".red(), filepath, e);
+                damaged.push((filepath, e.to_string()));
+            },
+        }
+    }
+    Ok(damaged)
+}
+
+/// Archives in `directory` and its subdirectories.
+fn collect_archives(directory: &Path, archives: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let filepath = entry?.path();
+        if filepath.is_dir() {
+            collect_archives(&filepath, archives)?;
+        } else if is_archive_candidate(&filepath) {
+            archives.push(filepath);
+        }
+    }
+    Ok(())
+}
+
+/// Whether fsck verifies `filepath`: a file named `BLADE_*.parquet`, any file
+/// whose parquet footer carries the manifest column, or a `.parquet` file whose
+/// footer cannot be read, eg: a truncated archive under a custom name. Readable
+/// parquet files without a manifest, eg: cryo chunks, are not archives.
+fn is_archive_candidate(filepath: &Path) -> bool {
+    let parquet = filepath.extension().is_some_and(|extension| extension == "parquet");
+    let name = filepath.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    if parquet && name.starts_with("BLADE_") {
+        return true;
+    }
+    match File::open(filepath).ok().and_then(|file| ParquetReader::new(file).schema().ok()) {
+        Some(schema) => schema.contains(MANIFEST_COLUMN),
+        None => parquet,
+    }
+}
@@ -0,0 +1,224 @@
+// Manifest describing the layout of a BLADE archive.
+// Streams are stacked horizontally in one parquet file, so shorter streams are
+// padded with nulls. The manifest records the true length of every stream and
+// which codec produced each original column so the reader can decode it.
+// Streams and the manifest itself carry CRC32 checksums verified on read.
+
+use crate::error::{BladeError, Result};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use super::integrity::{manifest_checksum, stream_checksum};
+
+pub const MANIFEST_COLUMN: &str = "blade_manifest";
+pub const MANIFEST_CHECKSUM_COLUMN: &str = "blade_manifest_checksum";
+pub const MANIFEST_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,                  // archive layout version
+    pub dataset: String,               // dataset name, eg: "transfers"
+    pub rows: usize,                   // row count of the original dataset
+    #[serde(default)]
+    pub start_block: Option<u32>,      // first block covered by the archive
+    #[serde(default)]
+    pub end_block: Option<u32>,        // last block covered by the archive
+    pub columns: Vec<ColumnEntry>,     // original columns and their codecs
+    pub streams: Vec<StreamEntry>,     // encoded streams and their lengths
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address_registry: Option<RegistryReference>,    // registry version address ids start from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dictionary_bundle: Option<BundleReference>,     // trained bundle the dictionaries start from
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ColumnEntry {
+    pub name: String,                  // original column name
+    pub codec: String,                 // codec used to encode the column
+    pub streams: Vec<String>,          // streams needed to decode the column
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StreamEntry {
+    pub name: String,                  // stream column name in the archive
+    pub len: usize,                    // stream length before null padding
+    pub checksum: u32,                 // CRC32 of the stream values
+    #[serde(default, skip_serializing_if = "StreamEncoding::is_plain")]
+    pub encoding: StreamEncoding,      // entropy coding applied when written
+}
+
+/// Version of the persistent address registry an archive was written against.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RegistryReference {
+    pub path: String,                  // registry file as given when compressing
+    pub version: usize,                // registry addresses the archive ids rely on
+    pub checksum: u32,                 // CRC32 of those addresses
+}
+
+/// Trained dictionary bundle an archive was written with.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BundleReference {
+    pub path: String,                  // bundle file as given when compressing
+    pub checksum: u32,                 // CRC32 of the bundle file
+}
+
+/// Final encoding of a stream in the archive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamEncoding {
+    #[default]
+    Plain,                             // stored as a column of values
+    Huffman,                           // u32 values, canonical Huffman coded into u32 words
+}
+
+impl StreamEncoding {
+
+    pub fn is_plain(&self) -> bool {
+        *self == StreamEncoding::Plain
+    }
+}
+
+/// Other columns a codec decodes from: keyed transaction hashes are rebuilt
+/// from (block_number, transaction_index), and hot amounts are looked up in the
+/// dictionary of each row's erc20 token.
+pub fn codec_dependencies(column: &str, codec: &str) -> &'static [&'static str] {
+    match (column, codec) {
+        ("transaction_hash", "keyed") => &["block_number", "transaction_index"],
+        ("value_string", "hot_dictionary") => &["erc20"],
+        _ => &[],
+    }
+}
+
+impl Manifest {
+
+    pub fn new(dataset: &str) -> Self {
+        Self {
+            version: MANIFEST_VERSION,
+            dataset: dataset.to_string(),
+            rows: 0,
+            start_block: None,
+            end_block: None,
+            columns: Vec::new(),
+            streams: Vec::new(),
+            address_registry: None,
+            dictionary_bundle: None,
+        }
+    }
+
+    /// Register an original column and the compressed dataframe(s) holding its streams.
+    pub fn add_column(&mut self, name: &str, codec: &str, dataframes: &[DataFrame]) -> Result<()> {
+        let streams = self.add_streams(dataframes)?;
+        self.columns.push(ColumnEntry {
+            name: name.to_string(),
+            codec: codec.to_string(),
+            streams,
+        });
+        Ok(())
+    }
+
+    /// Register the streams of compressed dataframe(s), returning their names.
+    /// Used directly for streams that belong to no single column, eg: the row order.
+    pub fn add_streams(&mut self, dataframes: &[DataFrame]) -> Result<Vec<String>> {
+        let mut streams = Vec::new();
+        for df in dataframes {
+            for column in df.get_columns() {
+                let stream = column.name().to_string();
+                if !self.streams.iter().any(|s| s.name == stream) {
+                    let checksum = stream_checksum(column)?;
+                    self.streams.push(StreamEntry { name: stream.clone(), len: df.height(), checksum, encoding: StreamEncoding::Plain });
+                }
+                streams.push(stream);
+            }
+        }
+        Ok(streams)
+    }
+
+    /// Look up the entry of an original column.
+    pub fn column(&self, name: &str) -> Option<&ColumnEntry> {
+        self.columns.iter().find(|c| c.name == name)
+    }
+
+    /// Look up the entry of a stream.
+    pub fn stream_mut(&mut self, name: &str) -> Option<&mut StreamEntry> {
+        self.streams.iter_mut().find(|s| s.name == name)
+    }
+
+    /// Names of the original columns whose decoding depends on a stream.
+    pub fn columns_using(&self, stream: &str) -> Vec<String> {
+        self.columns.iter()
+            .filter(|c| c.streams.iter().any(|s| s == stream))
+            .map(|c| c.name.clone())
+            .collect()
+    }
+
+    /// Streams needed to decode `columns`: their own streams, those of the
+    /// columns their codecs decode from, and the streams of no column, eg: the row order.
+    pub fn streams_for(&self, columns: &[String]) -> Result<Vec<String>> {
+        let mut needed: Vec<&ColumnEntry> = Vec::new();
+        let mut pending: Vec<String> = columns.to_vec();
+        while let Some(name) = pending.pop() {
+            if needed.iter().any(|entry| entry.name == name) {
+                continue;
+            }
+            let entry = self.column(&name)
+                .ok_or_else(|| BladeError::Schema(format!("column `{}` is not stored in the archive", name)))?;
+            pending.extend(codec_dependencies(&entry.name, &entry.codec).iter().map(|column| column.to_string()));
+            needed.push(entry);
+        }
+        Ok(self.streams.iter()
+            .filter(|stream| {
+                let users = self.columns_using(&stream.name);
+                users.is_empty() || needed.iter().any(|entry| users.contains(&entry.name))
+            })
+            .map(|stream| stream.name.clone())
+            .collect())
+    }
+
+    /// Verify a trimmed stream against the checksum recorded when it was written.
+    pub fn verify_stream(&self, entry: &StreamEntry, column: &Column) -> Result<()> {
+        let expected = entry.checksum;
+        let actual = stream_checksum(column)?;
+        if actual != expected {
+            return Err(BladeError::Integrity(format!(
+                "checksum mismatch in stream `{}` (columns {:?}): expected {:08x}, found {:08x}",
+                entry.name, self.columns_using(&entry.name), expected, actual
+            )));
+        }
+        Ok(())
+    }
+
+    /// Names of the original columns stored in the archive, in order.
+    pub fn column_names(&self) -> Vec<String> {
+        self.columns.iter().map(|c| c.name.clone()).collect()
+    }
+
+    /// Serialize the manifest into a single row dataframe for stacking with the streams.
+    pub fn to_dataframe(&self) -> Result<DataFrame> {
+        let json = serde_json::to_string(self)?;
+        let checksum = manifest_checksum(&json);
+        let s1 = Column::new(MANIFEST_COLUMN.into(), &[json]);
+        let s2 = Column::new(MANIFEST_CHECKSUM_COLUMN.into(), &[checksum]);
+        let df = DataFrame::new(vec![s1, s2])?;
+        Ok(df)
+    }
+
+    /// Parse the manifest from the stacked archive dataframe.
+    pub fn from_dataframe(dataset: &DataFrame) -> Result<Self> {
+        let column = dataset.column(MANIFEST_COLUMN)
+            .map_err(|_| BladeError::Format(format!("missing `{}` column, not a BLADE archive", MANIFEST_COLUMN)))?;
+        let json = column.str()?.get(0)
+            .ok_or_else(|| BladeError::Format(format!("empty `{}` column", MANIFEST_COLUMN)))?;
+        let expected = dataset.column(MANIFEST_CHECKSUM_COLUMN)
+            .map_err(|_| BladeError::Integrity(format!("missing `{}` column, the manifest cannot be verified", MANIFEST_CHECKSUM_COLUMN)))?
+            .u32()?.get(0)
+            .ok_or_else(|| BladeError::Integrity(format!("empty `{}` column", MANIFEST_CHECKSUM_COLUMN)))?;
+        let actual = manifest_checksum(json);
+        if expected != actual {
+            return Err(BladeError::Integrity(format!("checksum mismatch in `{}` column: expected {:08x}, found {:08x}", MANIFEST_COLUMN, expected, actual)));
+        }
+        let manifest: Manifest = serde_json::from_str(json)?;
+        if manifest.version > MANIFEST_VERSION {
+            return Err(BladeError::Format(format!("unsupported BLADE archive version {}", manifest.version)));
+        }
+        Ok(manifest)
+    }
+}
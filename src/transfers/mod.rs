@@ -1,5 +1,21 @@
 
+#[allow(clippy::module_inception)]
 pub mod transfers;
 pub mod ingestion;
 pub mod compression;
-pub mod writer;
\ No newline at end of file
+pub mod writer;
+pub mod reader;
+pub mod manifest;
+pub mod scan;
+pub mod record_batch;
+pub mod compaction;
+pub mod integrity;
+pub mod options;
+pub mod row_order;
+pub mod registry;
+pub mod bundle;
+pub mod record;
+pub mod output;
+pub mod export;
+pub mod analytics;
+pub mod chunks;
\ No newline at end of file
@@ -0,0 +1,24 @@
+// Options controlling how a transfers dataset is compressed.
+
+use std::path::PathBuf;
+use super::manifest::StreamEncoding;
+
+#[derive(Debug, Clone, Default)]
+pub struct CompressOptions {
+    pub entropy_coding: StreamEncoding,     // final coding of u32 index / count streams
+    pub reorder_rows: bool,                 // store rows in a compression friendly order within each block
+    pub address_registry: Option<PathBuf>,  // registry giving addresses stable ids across archives
+    pub dictionary_bundle: Option<PathBuf>, // trained bundle of addresses and amounts to start from
+}
+
+impl CompressOptions {
+
+    pub fn new() -> Self {
+        Self {
+            entropy_coding: StreamEncoding::Plain,
+            reorder_rows: false,
+            address_registry: None,
+            dictionary_bundle: None,
+        }
+    }
+}
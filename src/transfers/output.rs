@@ -0,0 +1,181 @@
+// Where compressed archives are written.
+// An archive is named from a template filled with the input's cryo naming parts
+// and block range, placed in an output directory (beside the input by default),
+// and an existing archive is failed on, skipped or replaced. In batch mode a
+// directory tree of cryo files is compressed, optionally mirrored under the
+// output directory.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use crate::error::{BladeError, Result};
+use super::ingestion::{parse_cryo_filename, InputFormat};
+use super::options::CompressOptions;
+use super::reader::is_blade_archive;
+use super::transfers::Transfer;
+
+/// Archive names of earlier releases: the input name prefixed with `BLADE_`, as parquet.
+pub const DEFAULT_NAMING: &str = "BLADE_{stem}.parquet";
+
+/// What to do when the archive to write already exists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    #[default]
+    Fail,           // error out, leaving the existing archive
+    Skip,           // keep the existing archive and do not compress the input
+    Replace,        // write the new archive over it
+}
+
+impl OverwritePolicy {
+
+    /// Whether a file may be written at `path`: true when it does not exist yet
+    /// or is replaced, false when skipped.
+    pub fn allows(&self, path: &Path) -> Result<bool> {
+        if !path.exists() {
+            return Ok(true);
+        }
+        match self {
+            OverwritePolicy::Fail => Err(BladeError::Format(format!("{:?} already exists (overwrite policy: fail)", path))),
+            OverwritePolicy::Skip => Ok(false),
+            OverwritePolicy::Replace => Ok(true),
+        }
+    }
+}
+
+impl FromStr for OverwritePolicy {
+    type Err = BladeError;
+
+    fn from_str(policy: &str) -> Result<Self> {
+        match policy {
+            "fail" => Ok(OverwritePolicy::Fail),
+            "skip" => Ok(OverwritePolicy::Skip),
+            "replace" => Ok(OverwritePolicy::Replace),
+            _ => Err(BladeError::Format(format!("unknown overwrite policy `{}`, expected fail, skip or replace", policy))),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OutputOptions {
+    pub directory: Option<PathBuf>,     // directory archives are written to; the input's directory when None
+    pub naming: String,                 // archive filename template, eg: `{chain}__{dataset}__{start}_to_{end}.blade`
+    pub mirror: bool,                   // batch mode: recreate the input tree under `directory`
+    pub overwrite: OverwritePolicy,     // existing archive handling
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OutputOptions {
+
+    pub fn new() -> Self {
+        Self {
+            directory: None,
+            naming: DEFAULT_NAMING.to_string(),
+            mirror: false,
+            overwrite: OverwritePolicy::Fail,
+        }
+    }
+
+    /// Archive filename for `input` covering blocks `start..=end`. The template
+    /// takes `{file}` and `{stem}` of the input name, `{chain}` and `{dataset}`
+    /// of a cryo filename, and the `{start}` / `{end}` blocks.
+    pub fn filename(&self, input: &Path, start: Option<u32>, end: Option<u32>) -> Result<String> {
+        let cryo = parse_cryo_filename(input);
+        let mut filename = String::new();
+        let mut rest = self.naming.as_str();
+        while let Some(open) = rest.find('{') {
+            filename.push_str(&rest[..open]);
+            let close = rest[open..].find('}')
+                .ok_or_else(|| BladeError::Format(format!("unclosed placeholder in naming template `{}`", self.naming)))?;
+            let placeholder = &rest[open + 1..open + close];
+            let value = match placeholder {
+                "file" => input.file_name().map(|name| name.to_string_lossy().to_string()),
+                "stem" => input.file_stem().map(|stem| stem.to_string_lossy().to_string()),
+                "chain" => cryo.as_ref().map(|cryo| cryo.chain.clone()),
+                "dataset" => cryo.as_ref().map(|cryo| cryo.dataset.clone()),
+                "start" => start.map(|block| block.to_string()),
+                "end" => end.map(|block| block.to_string()),
+                _ => return Err(BladeError::Format(format!("unknown placeholder `{{{}}}` in naming template `{}`", placeholder, self.naming))),
+            };
+            let value = value.ok_or_else(|| BladeError::Format(format!("no value for `{{{}}}` of naming template `{}` from {:?}", placeholder, self.naming, input)))?;
+            filename.push_str(&value);
+            rest = &rest[open + close + 1..];
+        }
+        filename.push_str(rest);
+        Ok(filename)
+    }
+
+    /// Path of the archive of `input` covering blocks `start..=end`, with
+    /// `relative` the input's directory below the batch root, mirrored when asked
+    /// to. `None` when an existing archive is skipped.
+    pub fn archive_path(&self, input: &Path, relative: Option<&Path>, start: Option<u32>, end: Option<u32>) -> Result<Option<PathBuf>> {
+        let directory = match (&self.directory, relative) {
+            (Some(directory), Some(relative)) if self.mirror => directory.join(relative),
+            (Some(directory), _) => directory.clone(),
+            (None, _) => input.parent().map(Path::to_path_buf).unwrap_or_default(),
+        };
+        let path = directory.join(self.filename(input, start, end)?);
+        ensure_not_input(&path, input)?;
+        Ok(self.overwrite.allows(&path)?.then_some(path))
+    }
+}
+
+/// Error when `path` is the file `input` itself, eg: named `{file}` beside it;
+/// whatever the overwrite policy, an input is never written over.
+pub fn ensure_not_input(path: &Path, input: &Path) -> Result<()> {
+    if !path.exists() || !input.exists() {
+        return Ok(());
+    }
+    if fs::canonicalize(path)? == fs::canonicalize(input)? {
+        return Err(BladeError::Format(format!("output {:?} is the input file itself; choose another naming template or output directory", path)));
+    }
+    Ok(())
+}
+
+/// Compress every cryo transfers file below `directory`, BLADE archives aside
+/// whatever their name.
+/// Returns the paths of the archives written; skipped inputs are left out.
+pub fn compress_directory(directory: &Path, options: &CompressOptions, output: &OutputOptions) -> Result<Vec<PathBuf>> {
+    // an output directory inside the tree holds archives, not inputs
+    let excluded = output.directory.as_ref().and_then(|directory| fs::canonicalize(directory).ok());
+    let mut inputs: Vec<PathBuf> = Vec::new();
+    collect_transfers_files(directory, excluded.as_deref(), &mut inputs)?;
+    inputs.sort();
+
+    let mut written: Vec<PathBuf> = Vec::new();
+    for filepath in inputs {
+        let relative = filepath.parent()
+            .and_then(|parent| parent.strip_prefix(directory).ok())
+            .map(Path::to_path_buf);
+        let mut transfer = Transfer::new();
+        transfer.options = options.clone();
+        transfer.output = output.clone();
+        if let Some(archive) = transfer.compress_into(&filepath, relative.as_deref())? {
+            written.push(archive);
+        }
+    }
+    Ok(written)
+}
+
+/// Cryo transfers files (parquet, CSV or JSON) below `directory`, recursively, outside of `excluded`.
+fn collect_transfers_files(directory: &Path, excluded: Option<&Path>, inputs: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if excluded.is_none() || fs::canonicalize(&path).ok().as_deref() != excluded {
+                collect_transfers_files(&path, excluded, inputs)?;
+            }
+            continue;
+        }
+        let is_transfers = InputFormat::from_path(&path).is_some()
+            && parse_cryo_filename(&path).is_some_and(|cryo| cryo.dataset.ends_with("transfers"));
+        if is_transfers && !is_blade_archive(&path) {
+            inputs.push(path);
+        }
+    }
+    Ok(())
+}
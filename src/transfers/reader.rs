@@ -0,0 +1,99 @@
+// Reader for BLADE archives written through `parquet_writer`.
+// Streams are stored side by side and padded with nulls to the longest stream,
+// so each one is trimmed back to the length recorded in the manifest, entropy
+// decoded when needed, and checked against its recorded checksum.
+
+use std::fs::File;
+use std::io::Cursor;
+use crate::error::{BladeError, Result};
+use std::path::{Path, PathBuf};
+use polars::prelude::*;
+use polars::io::mmap::MmapBytesReader;
+use super::manifest::{Manifest, StreamEncoding, MANIFEST_CHECKSUM_COLUMN, MANIFEST_COLUMN};
+use super::compression::{huffman, Streams};
+use super::registry::registry_stream;
+use super::bundle::open_referenced;
+
+
+/// Whether `filepath` is a BLADE archive: a parquet file carrying the manifest
+/// column, whatever it is named. Only the parquet footer is read.
+pub fn is_blade_archive(filepath: &Path) -> bool {
+    let Ok(file) = File::open(filepath) else {
+        return false;
+    };
+    ParquetReader::new(file).schema().is_ok_and(|schema| schema.contains(MANIFEST_COLUMN))
+}
+
+/// Read a BLADE archive, returning its manifest and the trimmed encoded streams.
+pub fn parquet_reader(filepath: &PathBuf) -> Result<(Manifest, Streams)> {
+    let file = File::open(filepath)?;
+    archive_reader(file, Some(filepath), None)
+}
+
+/// Read only the streams needed to decode `columns` of a BLADE archive,
+/// dependencies of their codecs included.
+pub fn parquet_projection_reader(filepath: &PathBuf, columns: &[String]) -> Result<(Manifest, Streams)> {
+    let file = File::open(filepath)?;
+    archive_reader(file, Some(filepath), Some(columns))
+}
+
+/// Read a BLADE archive held in memory, eg: received from a service. A
+/// registry or bundle it references is looked up at its recorded path only.
+pub fn archive_from_bytes(bytes: &[u8]) -> Result<(Manifest, Streams)> {
+    archive_reader(Cursor::new(bytes), None, None)
+}
+
+/// Read only the streams needed to decode `columns` of a BLADE archive held in memory.
+pub fn archive_projection_from_bytes(bytes: &[u8], columns: &[String]) -> Result<(Manifest, Streams)> {
+    archive_reader(Cursor::new(bytes), None, Some(columns))
+}
+
+/// Read a BLADE archive from any parquet source; `filepath` is the archive's
+/// location, used to find the registry or bundle it references. With a
+/// projection, the manifest is read first and then only the stream columns the
+/// projected columns decode from.
+fn archive_reader<R: MmapBytesReader>(mut source: R, filepath: Option<&Path>, columns: Option<&[String]>) -> Result<(Manifest, Streams)> {
+    let (manifest, df, needed) = match columns {
+        None => {
+            let df = ParquetReader::new(&mut source).finish()?;
+            let manifest = Manifest::from_dataframe(&df)?;
+            let needed: Vec<String> = manifest.streams.iter().map(|stream| stream.name.clone()).collect();
+            (manifest, df, needed)
+        },
+        Some(columns) => {
+            let header: Vec<String> = vec![MANIFEST_COLUMN.to_string(), MANIFEST_CHECKSUM_COLUMN.to_string()];
+            let manifest = Manifest::from_dataframe(&ParquetReader::new(&mut source).with_columns(Some(header)).finish()?)?;
+            let needed = manifest.streams_for(columns)?;
+            let df = ParquetReader::new(&mut source).with_columns(Some(needed.clone())).finish()?;
+            (manifest, df, needed)
+        },
+    };
+
+    let mut streams = Streams::new(manifest.rows);
+    for stream in manifest.streams.iter().filter(|stream| needed.contains(&stream.name)) {
+        let stored = df.column(&stream.name)
+            .map_err(|_| BladeError::Format(format!("missing stream `{}` (columns {:?})", stream.name, manifest.columns_using(&stream.name))))?;
+        let column = match stream.encoding {
+            StreamEncoding::Plain => stored.slice(0, stream.len),
+            StreamEncoding::Huffman => {
+                // coded words are followed by null padding only
+                let words: Vec<u32> = stored.u32()?.iter().map_while(|word| word).collect();
+                Column::new(stored.name().clone(), huffman::decode(&words, stream.len)?)
+            },
+        };
+        manifest.verify_stream(stream, &column)?;
+        streams.insert(column);
+    }
+
+    // address ids and hot amounts of the archive may start from a registry or
+    // trained bundle shared with other archives, needed by the vocabulary and
+    // hot dictionary streams only
+    let reads = |stream: &str| columns.is_none() || needed.iter().any(|name| name == stream);
+    if let Some(reference) = manifest.address_registry.as_ref().filter(|_| reads("address_vocabulary")) {
+        streams.insert(registry_stream(reference, filepath)?);
+    }
+    if let Some(reference) = manifest.dictionary_bundle.as_ref().filter(|_| reads("address_vocabulary") || reads("value_hot_index")) {
+        open_referenced(reference, filepath)?.insert_streams(&mut streams);
+    }
+    Ok((manifest, streams))
+}
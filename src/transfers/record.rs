@@ -0,0 +1,264 @@
+// Typed transfer rows.
+// `TransferRecord` is the Rust-level view of one transfers row: the hash and
+// addresses as fixed size byte arrays, the amount as a U256. Serialized, hashes
+// and addresses are 0x-prefixed hex and the amount a decimal string, matching
+// cryo's string columns; the JSON Schema is generated through schemars.
+// Records of an archive are decoded range by range and converted one at a time.
+
+use ethnum::U256;
+use polars::prelude::*;
+use schemars::schema::RootSchema;
+use schemars::{schema_for, JsonSchema};
+use serde::{Deserialize, Serialize};
+use crate::error::{BladeError, Result};
+use super::chunks::ArchiveChunks;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct TransferRecord {
+    pub block_number: u32,
+    pub transaction_index: u32,
+    pub log_index: u32,
+    #[serde(with = "hex_bytes")]
+    #[schemars(with = "String", regex(pattern = r"^0x[0-9a-fA-F]{64}$"))]
+    pub transaction_hash: [u8; 32],
+    #[serde(with = "hex_bytes")]
+    #[schemars(with = "String", regex(pattern = r"^0x[0-9a-fA-F]{40}$"))]
+    pub erc20: [u8; 20],
+    #[serde(with = "hex_bytes")]
+    #[schemars(with = "String", regex(pattern = r"^0x[0-9a-fA-F]{40}$"))]
+    pub from_address: [u8; 20],
+    #[serde(with = "hex_bytes")]
+    #[schemars(with = "String", regex(pattern = r"^0x[0-9a-fA-F]{40}$"))]
+    pub to_address: [u8; 20],
+    #[serde(with = "decimal")]
+    #[schemars(with = "String", regex(pattern = r"^[0-9]+$"))]
+    pub value: U256,
+    pub chain_id: u64,
+}
+
+/// JSON Schema of a serialized `TransferRecord`.
+pub fn transfer_record_schema() -> RootSchema {
+    schema_for!(TransferRecord)
+}
+
+/// Build a transfers dataframe in the cryo schema from typed records; the
+/// redundant value_binary and value_f64 columns are derived from the value.
+pub fn records_to_dataframe(records: impl IntoIterator<Item = TransferRecord>) -> Result<DataFrame> {
+    let records: Vec<TransferRecord> = records.into_iter().collect();
+    let hex = |bytes: &[u8]| format!("0x{}", hex::encode(bytes));
+    let df = DataFrame::new(vec![
+        Column::new("block_number".into(), records.iter().map(|r| r.block_number).collect::<Vec<u32>>()),
+        Column::new("transaction_index".into(), records.iter().map(|r| r.transaction_index).collect::<Vec<u32>>()),
+        Column::new("log_index".into(), records.iter().map(|r| r.log_index).collect::<Vec<u32>>()),
+        Column::new("transaction_hash".into(), records.iter().map(|r| hex(&r.transaction_hash)).collect::<Vec<String>>()),
+        Column::new("erc20".into(), records.iter().map(|r| hex(&r.erc20)).collect::<Vec<String>>()),
+        Column::new("from_address".into(), records.iter().map(|r| hex(&r.from_address)).collect::<Vec<String>>()),
+        Column::new("to_address".into(), records.iter().map(|r| hex(&r.to_address)).collect::<Vec<String>>()),
+        Column::new("value_string".into(), records.iter().map(|r| r.value.to_string()).collect::<Vec<String>>()),
+        Column::new("chain_id".into(), records.iter().map(|r| r.chain_id).collect::<Vec<u64>>()),
+    ])?;
+    with_derived_values(&df)
+}
+
+/// Add the value_binary and value_f64 columns archives drop back next to
+/// value_string, giving decoded transfers cryo's schema. Null values stay null.
+pub fn with_derived_values(df: &DataFrame) -> Result<DataFrame> {
+    let Ok(value_strings) = df.column("value_string") else { return Ok(df.clone()) };
+    let mut binaries: Vec<Option<String>> = Vec::with_capacity(df.height());
+    let mut floats: Vec<Option<f64>> = Vec::with_capacity(df.height());
+    for (row, value) in value_strings.str()?.iter().enumerate() {
+        let value = value.map(|value| U256::from_str_radix(value, 10)
+            .map_err(|e| BladeError::malformed("value_string", row, format!("`{}` is not a u256: {}", value, e))))
+            .transpose()?;
+        binaries.push(value.map(|value| format!("0x{:064x}", value)));
+        floats.push(value.map(|value| value.as_f64()));
+    }
+
+    let mut columns: Vec<Column> = Vec::with_capacity(df.width() + 2);
+    for column in df.get_columns() {
+        if column.name() == "value_string" {
+            columns.push(Column::new("value_binary".into(), std::mem::take(&mut binaries)));
+            columns.push(column.clone());
+            columns.push(Column::new("value_f64".into(), std::mem::take(&mut floats)));
+        } else {
+            columns.push(column.clone());
+        }
+    }
+    Ok(DataFrame::new(columns)?)
+}
+
+/// Columns a `TransferRecord` is built from, as stored in an archive.
+pub const RECORD_COLUMNS: [&str; 9] = [
+    "block_number", "transaction_index", "log_index", "transaction_hash",
+    "erc20", "from_address", "to_address", "value_string", "chain_id",
+];
+
+/// Iterator of typed records over decoded transfers: an archive decoded one
+/// range of rows at a time, or a dataframe held in memory. Rows are converted
+/// one at a time; a null or malformed value is an error for its row.
+pub struct TransferRecordIter {
+    chunks: Option<ArchiveChunks>,   // ranges still to decode; None for a dataframe
+    columns: Option<RecordColumns>,  // decoded range being converted
+    offset: usize,                   // row of the first row of the range
+    row: usize,                      // next row of the range to convert
+    rows: usize,                     // rows of the whole dataset
+}
+
+impl TransferRecordIter {
+
+    pub fn new(df: DataFrame) -> Result<Self> {
+        Ok(Self {
+            chunks: None,
+            rows: df.height(),
+            columns: Some(RecordColumns::new(df)?),
+            offset: 0,
+            row: 0,
+        })
+    }
+
+    /// Records of an archive decoded range by range; only one range of
+    /// decoded rows is held at a time.
+    pub fn from_chunks(chunks: ArchiveChunks) -> Self {
+        Self {
+            rows: chunks.rows(),
+            chunks: Some(chunks),
+            columns: None,
+            offset: 0,
+            row: 0,
+        }
+    }
+}
+
+impl Iterator for TransferRecordIter {
+    type Item = Result<TransferRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(columns) = &self.columns {
+                if self.row < columns.len() {
+                    let record = columns.record(self.row, self.offset + self.row);
+                    self.row += 1;
+                    return Some(record);
+                }
+            }
+
+            // the range is converted: decode the next one
+            let decoded = self.chunks.as_mut()?.next()?;
+            self.offset += self.columns.take().map_or(0, |columns| columns.len());
+            self.row = 0;
+            match decoded.and_then(RecordColumns::new) {
+                Ok(columns) => self.columns = Some(columns),
+                Err(e) => {
+                    self.chunks = None;
+                    self.rows = 0;
+                    return Some(Err(e));
+                },
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.rows.saturating_sub(self.offset + self.row);
+        (remaining, Some(remaining))
+    }
+}
+
+/// Record columns of one decoded range.
+struct RecordColumns {
+    block_numbers: UInt32Chunked,
+    transaction_indexes: UInt32Chunked,
+    log_indexes: UInt32Chunked,
+    transaction_hashes: StringChunked,
+    tokens: StringChunked,
+    from_addresses: StringChunked,
+    to_addresses: StringChunked,
+    values: StringChunked,
+    chain_ids: UInt64Chunked,
+}
+
+impl RecordColumns {
+
+    fn new(mut df: DataFrame) -> Result<Self> {
+        df.align_chunks();
+        Ok(Self {
+            block_numbers: df.column("block_number")?.u32()?.clone(),
+            transaction_indexes: df.column("transaction_index")?.u32()?.clone(),
+            log_indexes: df.column("log_index")?.u32()?.clone(),
+            transaction_hashes: df.column("transaction_hash")?.str()?.clone(),
+            tokens: df.column("erc20")?.str()?.clone(),
+            from_addresses: df.column("from_address")?.str()?.clone(),
+            to_addresses: df.column("to_address")?.str()?.clone(),
+            values: df.column("value_string")?.str()?.clone(),
+            chain_ids: df.column("chain_id")?.u64()?.clone(),
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.block_numbers.len()
+    }
+
+    /// Record of `row`, reported as row `at` of the dataset when malformed.
+    fn record(&self, row: usize, at: usize) -> Result<TransferRecord> {
+        let value = non_null("value_string", at, self.values.get(row))?;
+        Ok(TransferRecord {
+            block_number: non_null("block_number", at, self.block_numbers.get(row))?,
+            transaction_index: non_null("transaction_index", at, self.transaction_indexes.get(row))?,
+            log_index: non_null("log_index", at, self.log_indexes.get(row))?,
+            transaction_hash: hex_field("transaction_hash", at, self.transaction_hashes.get(row))?,
+            erc20: hex_field("erc20", at, self.tokens.get(row))?,
+            from_address: hex_field("from_address", at, self.from_addresses.get(row))?,
+            to_address: hex_field("to_address", at, self.to_addresses.get(row))?,
+            value: U256::from_str_radix(value, 10)
+                .map_err(|e| BladeError::malformed("value_string", at, format!("`{}` is not a u256: {}", value, e)))?,
+            chain_id: non_null("chain_id", at, self.chain_ids.get(row))?,
+        })
+    }
+}
+
+fn non_null<T>(column: &str, row: usize, value: Option<T>) -> Result<T> {
+    value.ok_or_else(|| BladeError::malformed(column, row, "null value has no typed representation"))
+}
+
+fn hex_field<const N: usize>(column: &str, row: usize, value: Option<&str>) -> Result<[u8; N]> {
+    let value = non_null(column, row, value)?;
+    parse_hex(value).map_err(|message| BladeError::malformed(column, row, message))
+}
+
+/// Parse a 0x-prefixed hex string of exactly N bytes.
+fn parse_hex<const N: usize>(value: &str) -> std::result::Result<[u8; N], String> {
+    let hex = value.strip_prefix("0x").ok_or_else(|| format!("`{}` is missing the 0x prefix", value))?;
+    let mut bytes = [0u8; N];
+    hex::decode_to_slice(hex, &mut bytes).map_err(|e| format!("`{}` is not {} bytes of hex: {}", value, N, e))?;
+    Ok(bytes)
+}
+
+/// Serde of byte arrays as 0x-prefixed hex strings.
+mod hex_bytes {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer, const N: usize>(bytes: &[u8; N], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{}", hex::encode(bytes)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(deserializer: D) -> Result<[u8; N], D::Error> {
+        let value = String::deserialize(deserializer)?;
+        super::parse_hex(&value).map_err(D::Error::custom)
+    }
+}
+
+/// Serde of a U256 as a decimal string.
+mod decimal {
+    use ethnum::U256;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &U256, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        U256::from_str_radix(&value, 10).map_err(|e| D::Error::custom(format!("`{}` is not a u256: {}", value, e)))
+    }
+}
@@ -0,0 +1,135 @@
+// Conversion between arrow-rs RecordBatches and polars DataFrames.
+// Lets services built on arrow-rs / DataFusion hand transfers to Blade and read
+// decoded transfers back without going through parquet files.
+
+use std::sync::Arc;
+use crate::error::{BladeError, Result};
+use polars::prelude::*;
+use arrow::array::{
+    Array, ArrayRef, AsArray, Float64Array, StringArray, UInt32Array, UInt64Array,
+};
+use arrow::datatypes::{
+    DataType as ArrowDataType, Field as ArrowField, Float64Type, Schema as ArrowSchema,
+    SchemaRef as ArrowSchemaRef, UInt32Type, UInt64Type,
+};
+use arrow::error::ArrowError;
+use arrow::record_batch::{RecordBatch, RecordBatchReader};
+use super::record::with_derived_values;
+
+
+/// Convert one RecordBatch into a DataFrame with matching column names and types.
+pub fn record_batch_to_dataframe(batch: &RecordBatch) -> Result<DataFrame> {
+    let mut columns: Vec<Column> = Vec::with_capacity(batch.num_columns());
+    for (field, array) in batch.schema().fields().iter().zip(batch.columns()) {
+        let name: PlSmallStr = field.name().as_str().into();
+        let column = match array.data_type() {
+            ArrowDataType::UInt32 => {
+                let values: Vec<Option<u32>> = array.as_primitive::<UInt32Type>().iter().collect();
+                Column::new(name, values)
+            },
+            ArrowDataType::UInt64 => {
+                let values: Vec<Option<u64>> = array.as_primitive::<UInt64Type>().iter().collect();
+                Column::new(name, values)
+            },
+            ArrowDataType::Float64 => {
+                let values: Vec<Option<f64>> = array.as_primitive::<Float64Type>().iter().collect();
+                Column::new(name, values)
+            },
+            ArrowDataType::Utf8 => {
+                let values: Vec<Option<&str>> = array.as_string::<i32>().iter().collect();
+                Column::new(name, values)
+            },
+            ArrowDataType::LargeUtf8 => {
+                let values: Vec<Option<&str>> = array.as_string::<i64>().iter().collect();
+                Column::new(name, values)
+            },
+            ArrowDataType::Utf8View => {
+                let values: Vec<Option<&str>> = array.as_string_view().iter().collect();
+                Column::new(name, values)
+            },
+            other => return Err(BladeError::Schema(format!("unsupported arrow type {} for column `{}`", other, field.name()))),
+        };
+        columns.push(column);
+    }
+    Ok(DataFrame::new(columns)?)
+}
+
+/// Convert and stack several RecordBatches into a single DataFrame.
+pub fn record_batches_to_dataframe(batches: &[RecordBatch]) -> Result<DataFrame> {
+    let mut dataframes = batches.iter().map(record_batch_to_dataframe);
+    let mut df = match dataframes.next() {
+        Some(df) => df?,
+        None => return Err(BladeError::Format("no record batches to compress".to_string())),
+    };
+    for other in dataframes {
+        df.vstack_mut(&other?)?;
+    }
+    df.align_chunks();
+    Ok(df)
+}
+
+/// Convert a DataFrame into a RecordBatch with matching column names and types.
+pub fn dataframe_to_record_batch(df: &DataFrame) -> Result<RecordBatch> {
+    let mut fields: Vec<ArrowField> = Vec::with_capacity(df.width());
+    let mut arrays: Vec<ArrayRef> = Vec::with_capacity(df.width());
+    for column in df.get_columns() {
+        let array: ArrayRef = match column.dtype() {
+            DataType::UInt32 => Arc::new(column.u32()?.iter().collect::<UInt32Array>()),
+            DataType::UInt64 => Arc::new(column.u64()?.iter().collect::<UInt64Array>()),
+            DataType::Float64 => Arc::new(column.f64()?.iter().collect::<Float64Array>()),
+            DataType::String => Arc::new(column.str()?.iter().collect::<StringArray>()),
+            other => return Err(BladeError::Schema(format!("unsupported polars type {} for column `{}`", other, column.name()))),
+        };
+        fields.push(ArrowField::new(column.name().as_str(), array.data_type().clone(), true));
+        arrays.push(array);
+    }
+    let schema = Arc::new(ArrowSchema::new(fields));
+    Ok(RecordBatch::try_new(schema, arrays)?)
+}
+
+
+/// Iterator of RecordBatches over a decoded transfers dataset held in memory.
+/// Each batch is sliced, given value_binary / value_f64 and converted to arrow
+/// only when it is read, so batches have the schema transfers are compressed from.
+pub struct TransferRecordBatchReader {
+    pub dataset: DataFrame,         // decoded transfers
+    pub batch_size: usize,          // max rows per batch
+    pub offset: usize,              // next row to emit
+    pub schema: ArrowSchemaRef,     // arrow schema of every batch
+}
+
+impl TransferRecordBatchReader {
+
+    pub fn new(dataset: DataFrame, batch_size: usize) -> Result<Self> {
+        if batch_size == 0 {
+            return Err(BladeError::Format("batch_size must be greater than zero".to_string()));
+        }
+        let schema = dataframe_to_record_batch(&with_derived_values(&dataset.head(Some(0)))?)?.schema();
+        Ok(Self {
+            dataset,
+            batch_size,
+            offset: 0,
+            schema,
+        })
+    }
+}
+
+impl Iterator for TransferRecordBatchReader {
+    type Item = std::result::Result<RecordBatch, ArrowError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.dataset.height() {
+            return None;
+        }
+        let slice = self.dataset.slice(self.offset as i64, self.batch_size);
+        self.offset += slice.height();
+        let batch = with_derived_values(&slice).and_then(|slice| dataframe_to_record_batch(&slice));
+        Some(batch.map_err(|e| ArrowError::ExternalError(e.into())))
+    }
+}
+
+impl RecordBatchReader for TransferRecordBatchReader {
+    fn schema(&self) -> ArrowSchemaRef {
+        self.schema.clone()
+    }
+}
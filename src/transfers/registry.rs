@@ -0,0 +1,100 @@
+// Persistent address registry shared across BLADE archives.
+// The registry gives every address a stable id: archives compressed against it
+// store vocabulary ids and only the addresses it did not hold yet, and those
+// addresses are registered once the archive is written. Addresses are only ever
+// appended, so a registry version is its address count and every version is a
+// prefix of the later ones.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use polars::prelude::*;
+use crate::error::{BladeError, Result};
+use super::integrity::stream_checksum;
+use super::manifest::RegistryReference;
+use super::writer::atomic_write;
+
+/// Stream holding the registry addresses an archive relies on, added by the reader.
+pub const REGISTRY_STREAM: &str = "address_registry";
+
+pub struct AddressRegistry {
+    pub path: PathBuf,               // registry file
+    pub addresses: Vec<String>,      // registered addresses without '0x', in id order
+}
+
+impl AddressRegistry {
+
+    /// Open the registry at `path`; a missing file is an empty registry.
+    pub fn open(path: &Path) -> Result<Self> {
+        let addresses = match path.exists() {
+            true => {
+                let df = ParquetReader::new(File::open(path)?).finish()?;
+                df.column("address")?.str()?.into_no_null_iter().map(str::to_string).collect()
+            },
+            false => Vec::new(),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            addresses,
+        })
+    }
+
+    /// Reference to the current version, recorded in the manifest of archives using it.
+    pub fn reference(&self) -> Result<RegistryReference> {
+        Ok(RegistryReference {
+            path: self.path.to_string_lossy().to_string(),
+            version: self.addresses.len(),
+            checksum: stream_checksum(&Column::new(REGISTRY_STREAM.into(), &self.addresses))?,
+        })
+    }
+
+    /// Register the addresses the registry does not hold yet, returning how many were added.
+    pub fn register<'a>(&mut self, addresses: impl Iterator<Item = &'a str>) -> usize {
+        let registered = self.addresses.len();
+        let mut known: HashSet<String> = self.addresses.iter().cloned().collect();
+        for address in addresses {
+            if known.insert(address.to_string()) {
+                self.addresses.push(address.to_string());
+            }
+        }
+        self.addresses.len() - registered
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let mut df = DataFrame::new(vec![Column::new("address".into(), &self.addresses)])?;
+        atomic_write(&self.path, |file| {
+            ParquetWriter::new(file).finish(&mut df)?;
+            Ok(())
+        })
+    }
+}
+
+/// Load the registry addresses an archive was written against as the
+/// `address_registry` stream. The registry is looked up at its recorded path,
+/// then beside the archive, and must still start with the referenced version.
+pub fn registry_stream(reference: &RegistryReference, archive_filepath: Option<&Path>) -> Result<Column> {
+    let path = locate_beside_archive(&reference.path, archive_filepath, "address registry")?;
+    let registry = AddressRegistry::open(&path)?;
+    if registry.addresses.len() < reference.version {
+        return Err(BladeError::Integrity(format!("address registry {:?} holds {} addresses, archive needs version {}", path, registry.addresses.len(), reference.version)));
+    }
+    let column = Column::new(REGISTRY_STREAM.into(), &registry.addresses[..reference.version]);
+    let checksum = stream_checksum(&column)?;
+    if checksum != reference.checksum {
+        return Err(BladeError::Integrity(format!("address registry {:?} does not match version {}: expected {:08x}, found {:08x}", path, reference.version, reference.checksum, checksum)));
+    }
+    Ok(column)
+}
+
+/// Find a file an archive references: at its recorded path, else under the same
+/// name in the archive's directory when the archive was read from a file.
+pub fn locate_beside_archive(recorded: &str, archive_filepath: Option<&Path>, kind: &str) -> Result<PathBuf> {
+    let recorded = PathBuf::from(recorded);
+    let beside = archive_filepath.and_then(Path::parent).zip(recorded.file_name()).map(|(directory, name)| directory.join(name));
+    std::iter::once(recorded.clone()).chain(beside)
+        .find(|path| path.exists())
+        .ok_or_else(|| {
+            let archive = archive_filepath.map_or("an in-memory archive".to_string(), |filepath| format!("{:?}", filepath));
+            BladeError::Format(format!("{} {:?} referenced by {} not found", kind, recorded, archive))
+        })
+}
@@ -0,0 +1,123 @@
+// Compression friendly row order for transfers datasets.
+// Rows of each block are stably sorted by (erc20, from_address) so that token
+// and address runs grow longer. The permutation back to cryo's order is stored
+// as runs of consecutive original positions; each run keeps the shift from its
+// stored position, which stays small when rows only move within their block.
+
+use polars::prelude::*;
+use crate::error::{BladeError, Result};
+use super::compression::Streams;
+
+#[derive(Debug, Clone, Default)]
+pub struct RowOrder {
+    pub starts: Vec<u32>,     // original position of the first stored row of each run
+    pub lengths: Vec<u32>,    // stored rows of each run, at consecutive original positions
+}
+
+impl RowOrder {
+
+    pub fn new() -> Self {
+        Self {
+            starts: Vec::new(),
+            lengths: Vec::new(),
+        }
+    }
+
+    /// Order of `rows` rows stored as they came.
+    pub fn identity(rows: usize) -> Self {
+        let mut order = Self::new();
+        if rows > 0 {
+            order.starts.push(0);
+            order.lengths.push(rows as u32);
+        }
+        order
+    }
+
+    /// Append stored rows, given the original position of each.
+    pub fn extend(&mut self, positions: &[u32]) {
+        for position in positions {
+            match (self.starts.last(), self.lengths.last_mut()) {
+                (Some(start), Some(length)) if start + *length == *position => *length += 1,
+                _ => {
+                    self.starts.push(*position);
+                    self.lengths.push(1);
+                },
+            }
+        }
+    }
+
+    /// Original position of every stored row.
+    pub fn positions(&self) -> Vec<u32> {
+        self.starts.iter().zip(self.lengths.iter())
+            .flat_map(|(start, length)| *start..*start + *length)
+            .collect()
+    }
+
+    pub fn create_compressed_df(&self) -> Result<DataFrame> {
+        // shift of each run: original position of its first row minus its stored position
+        let mut stored = 0i64;
+        let mut shifts: Vec<i64> = Vec::with_capacity(self.starts.len());
+        for (start, length) in self.starts.iter().zip(self.lengths.iter()) {
+            shifts.push(*start as i64 - stored);
+            stored += *length as i64;
+        }
+        let s1 = Column::new("row_order_shifts".into(), shifts);
+        let s2 = Column::new("row_order_lengths".into(), &self.lengths);
+        Ok(DataFrame::new(vec![s1, s2])?)
+    }
+
+    /// Load the row order from the archive streams; `None` when rows are stored
+    /// in their original order.
+    pub fn load(streams: &Streams) -> Result<Option<Self>> {
+        if !streams.contains("row_order_shifts") {
+            return Ok(None);
+        }
+        let mut order = Self::new();
+        let mut stored = 0i64;
+        let shifts = streams.get("row_order_shifts")?.i64()?;
+        let lengths = streams.get("row_order_lengths")?.u32()?;
+        for (shift, length) in shifts.into_no_null_iter().zip(lengths.into_no_null_iter()) {
+            let start = u32::try_from(stored + shift)
+                .map_err(|_| BladeError::Integrity(format!("row order run at stored row {} starts outside the archive", stored)))?;
+            order.starts.push(start);
+            order.lengths.push(length);
+            stored += length as i64;
+        }
+        Ok(Some(order))
+    }
+
+    /// Put the rows of a dataframe decoded in stored order back in their original order.
+    pub fn restore(&self, df: &DataFrame) -> Result<DataFrame> {
+        let positions = self.positions();
+        if positions.len() != df.height() {
+            return Err(BladeError::Integrity(format!("row order covers {} rows, expected {}", positions.len(), df.height())));
+        }
+
+        // stored row of every original position
+        let mut inverse: Vec<IdxSize> = vec![IdxSize::MAX; positions.len()];
+        for (stored, original) in positions.iter().enumerate() {
+            match inverse.get_mut(*original as usize) {
+                Some(slot) if *slot == IdxSize::MAX => *slot = stored as IdxSize,
+                _ => return Err(BladeError::Integrity(format!("row order is not a permutation at original row {}", original))),
+            }
+        }
+        Ok(df.take(&IdxCa::from_vec("".into(), inverse))?)
+    }
+}
+
+/// Stored order of `dataset`: each run of rows with equal block_number, stably
+/// sorted by (erc20, from_address). Returns the original position of every stored row.
+pub fn compression_order(dataset: &DataFrame) -> Result<Vec<u32>> {
+    let blocks: Vec<Option<u32>> = dataset.column("block_number")?.u32()?.iter().collect();
+    let tokens: Vec<Option<&str>> = dataset.column("erc20")?.str()?.iter().collect();
+    let from_addresses: Vec<Option<&str>> = dataset.column("from_address")?.str()?.iter().collect();
+
+    let mut order: Vec<u32> = (0..dataset.height() as u32).collect();
+    let mut start = 0;
+    while start < order.len() {
+        let end = start + blocks[start..].iter().take_while(|block| **block == blocks[start]).count();
+        order[start..end].sort_by_key(|row| (tokens[*row as usize], from_addresses[*row as usize]));
+        start = end;
+    }
+    Ok(order)
+}
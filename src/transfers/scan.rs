@@ -0,0 +1,156 @@
+// Polars LazyFrame scan over BLADE archives.
+// `scan_blade` registers the archive as an anonymous scan source so it can be
+// queried like `scan_parquet`. Projections and predicates are pushed into the
+// decoders: only the codecs of the columns a query touches are run, and
+// predicate columns are decoded first to filter the rest. The scan has cryo's
+// schema: value_binary / value_f64 are derived from value_string when selected.
+
+use std::any::Any;
+use std::path::PathBuf;
+use std::sync::Arc;
+use polars::prelude::*;
+use crate::error::{BladeError, Result};
+
+use super::compression::Streams;
+use super::export::{stored_columns, DERIVED_COLUMNS};
+use super::ingestion::TransferIngestion;
+use super::manifest::Manifest;
+use super::reader::parquet_projection_reader;
+use super::record::with_derived_values;
+use super::transfers::Transfer;
+
+
+/// Lazily scan a BLADE archive; nothing is decoded until the query is collected.
+pub fn scan_blade(filepath: impl Into<PathBuf>) -> Result<LazyFrame> {
+    let scan = BladeScan::new(filepath.into())?;
+    let args = ScanArgsAnonymous {
+        schema: Some(scan.schema.clone()),
+        name: "BLADE SCAN",
+        ..Default::default()
+    };
+    Ok(LazyFrame::anonymous_scan(Arc::new(scan), args)?)
+}
+
+pub struct BladeScan {
+    pub filepath: PathBuf,          // BLADE archive being scanned
+    pub schema: SchemaRef,          // decoded schema of the archive
+}
+
+impl BladeScan {
+
+    pub fn new(filepath: PathBuf) -> Result<Self> {
+        // an empty projection reads the manifest only
+        let (manifest, _) = parquet_projection_reader(&filepath, &[])?;
+        let schema = Arc::new(decoded_schema(&manifest)?);
+        Ok(Self {
+            filepath,
+            schema,
+        })
+    }
+}
+
+impl AnonymousScan for BladeScan {
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self, _infer_schema_length: Option<usize>) -> PolarsResult<SchemaRef> {
+        Ok(self.schema.clone())
+    }
+
+    fn scan(&self, scan_opts: AnonymousScanArgs) -> PolarsResult<DataFrame> {
+        // Projected columns; every stored column when no projection was pushed down
+        let projection: Vec<String> = match &scan_opts.with_columns {
+            Some(columns) => columns.iter().map(|c| c.to_string()).collect(),
+            None => self.schema.iter_names().map(|c| c.to_string()).collect(),
+        };
+        let mut predicate_columns: Vec<String> = Vec::new();
+        if let Some(predicate) = &scan_opts.predicate {
+            for name in predicate.clone().meta().root_names() {
+                if !predicate_columns.iter().any(|c| c == name.as_str()) {
+                    predicate_columns.push(name.to_string());
+                }
+            }
+        }
+
+        // Read only the streams the projected and predicate columns decode from
+        let mut columns = projection.clone();
+        columns.extend(predicate_columns.iter().filter(|c| !projection.contains(c)).cloned());
+        let (manifest, streams) = parquet_projection_reader(&self.filepath, &stored_columns(&columns))?;
+        let mut transfer = Transfer::new();
+        transfer.manifest = manifest;
+
+        let mut df = match &scan_opts.predicate {
+            Some(predicate) => {
+                // Decode the predicate columns first and evaluate the predicate on them
+                let predicate_df = decode_columns(&mut transfer, &streams, &predicate_columns)?;
+                let mask_df = predicate_df.clone().lazy()
+                    .select([predicate.clone().alias("blade_predicate")])
+                    .collect()?;
+                let mask = mask_df.column("blade_predicate")?.bool()?.clone();
+                let mut df = predicate_df.filter(&mask)?;
+
+                // Decode the remaining projected columns and keep matching rows only
+                let remaining: Vec<String> = projection.iter()
+                    .filter(|c| !predicate_columns.contains(c))
+                    .cloned()
+                    .collect();
+                if !remaining.is_empty() {
+                    let remaining_df = decode_columns(&mut transfer, &streams, &remaining)?;
+                    df.hstack_mut(remaining_df.filter(&mask)?.get_columns())?;
+                }
+                df
+            },
+            None => decode_columns(&mut transfer, &streams, &projection)?,
+        };
+
+        // Restore projection order and apply any pushed down row limit
+        df = df.select(projection)?;
+        if let Some(n_rows) = scan_opts.n_rows {
+            df = df.head(Some(n_rows));
+        }
+        Ok(df)
+    }
+
+    fn allows_predicate_pushdown(&self) -> bool {
+        true
+    }
+
+    fn allows_projection_pushdown(&self) -> bool {
+        true
+    }
+
+    fn allows_slice_pushdown(&self) -> bool {
+        true
+    }
+}
+
+
+/// Decode `columns` from the archive streams: stored columns through their
+/// codecs, value_binary / value_f64 derived from value_string.
+fn decode_columns(transfer: &mut Transfer, streams: &Streams, columns: &[String]) -> Result<DataFrame> {
+    let mut df = transfer.decompress_columns(streams, &stored_columns(columns))?;
+    if columns.iter().any(|column| DERIVED_COLUMNS.contains(&column.as_str())) {
+        df = with_derived_values(&df)?;
+    }
+    Ok(df.select(columns.iter().map(String::as_str))?)
+}
+
+/// Schema of the decoded archive, taken from the reference transfers schema;
+/// value_binary / value_f64 sit next to value_string as `with_derived_values` puts them.
+pub fn decoded_schema(manifest: &Manifest) -> Result<Schema> {
+    let mut ingestion = TransferIngestion::new();
+    ingestion._generate_reference()?;
+    let reference = ingestion.reference_df.schema();
+
+    let mut stored = Schema::default();
+    for name in manifest.column_names() {
+        let dtype = reference.get(name.as_str())
+            .ok_or_else(|| BladeError::Schema(format!("no reference type for column `{}`", name)))?;
+        stored.with_column(name.into(), dtype.clone());
+    }
+    let derived = with_derived_values(&DataFrame::empty_with_schema(&stored))?;
+    Ok(derived.schema().as_ref().clone())
+}
+
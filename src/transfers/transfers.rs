@@ -1,30 +1,139 @@
 // external packages
-use std::path::PathBuf;
-use std::time::Instant;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use polars::prelude::*;
-use anyhow::Result;
-use owo_colors::OwoColorize;
+use crate::error::{BladeError, Result};
+use arrow::record_batch::{RecordBatch, RecordBatchReader};
+use rayon::prelude::*;
 
 // internal code
-use super::ingestion::TransferIngestion;
+use super::ingestion::{parse_cryo_filename, read_transfers, InputFormat, TransferIngestion};
 use crate::transfers::compression::{
     RLECompressedBlockNumberSeries, 
     RLECompressedTransactionIndexSeries, 
     RLECompressedLogIndexSeries,
+    RLECompressedTransactionHashSeries,
     DictionaryCompressedTransactionHashSeries,
+    KeyedCompressedTransactionHashSeries,
     RLECompressedErc20Series,
-    DictionaryCompressedFromAddressSeries,
-    DictionaryCompressedToAddressSeries,
+    DictionaryCompressedErc20Series,
     DictionaryCompressedAddressSeries,
-    NormalizedCompressedValueStrings,
-    RLECompressedChainIdSeries
+    SharedVocabularyCompressedAddressSeries,
+    MantissaExponentCompressedValueStrings,
+    HotDictionaryCompressedValueStrings,
+    RLECompressedChainIdSeries,
+    Streams,
 };
-use super::writer::parquet_writer;
+use super::manifest::{Manifest, StreamEncoding};
+use super::options::CompressOptions;
+use super::output::OutputOptions;
+use super::row_order::{compression_order, RowOrder};
+use super::registry::AddressRegistry;
+use super::bundle::DictionaryBundle;
+use crate::transfers::compression::huffman;
+use crate::transfers::compression::address_vocabulary::VOCABULARY_COLUMNS;
+use super::writer::{parquet_sink_writer, parquet_size, parquet_writer, stream_cost};
+use super::reader::{archive_from_bytes, archive_projection_from_bytes, parquet_projection_reader, parquet_reader};
+use super::record_batch::{record_batches_to_dataframe, TransferRecordBatchReader};
+use super::record::{records_to_dataframe, TransferRecord, TransferRecordIter, RECORD_COLUMNS};
+use super::chunks::{ArchiveChunks, CHUNK_ROWS};
+
+/// One column codec, run as a task by `compress_columns`.
+type CodecStep<'a> = Box<dyn Fn() -> Result<EncodedColumns> + Send + Sync + 'a>;
+
+/// Compressed dataframes of one codec, and the original columns they encode.
+struct EncodedColumns {
+    columns: Vec<(&'static str, &'static str, Vec<usize>)>,     // (original column, codec, dataframes it decodes from)
+    dataframes: Vec<DataFrame>,                                 // compressed stream dataframes
+}
+
+impl EncodedColumns {
+
+    /// Columns that each decode from all of `dataframes`.
+    fn new(columns: &[(&'static str, &'static str)], dataframes: Vec<DataFrame>) -> Self {
+        let all: Vec<usize> = (0..dataframes.len()).collect();
+        Self {
+            columns: columns.iter().map(|(name, codec)| (*name, *codec, all.clone())).collect(),
+            dataframes,
+        }
+    }
+
+    /// Add the columns of another codec, after the dataframes of this one.
+    fn extend(&mut self, other: EncodedColumns) {
+        let offset = self.dataframes.len();
+        for (name, codec, dataframes) in other.columns {
+            self.columns.push((name, codec, dataframes.iter().map(|i| i + offset).collect()));
+        }
+        self.dataframes.extend(other.dataframes);
+    }
+}
+
+/// erc20 through the token dictionary and from / to through the address pair
+/// dictionary, continuing `base` streams in the modes they were written with.
+fn encode_token_and_address_pairs(schema_check: &DataFrame, base: Option<&Streams>) -> Result<EncodedColumns> {
+    let mut encoded = match base.filter(|streams| !streams.contains("token_dictionary")) {
+        // archives written with token runs keep appending runs
+        Some(streams) => {
+            let mut token_compression = RLECompressedErc20Series::new();
+            token_compression.load(streams)?;
+            let compressed_tokens_df = token_compression.create_compressed_df(schema_check)?;
+            EncodedColumns::new(&[("erc20", "rle")], vec![compressed_tokens_df])
+        },
+        None => {
+            let mut token_compression = DictionaryCompressedErc20Series::new();
+            if let Some(streams) = base {
+                token_compression.load(streams)?;
+            }
+            let compressed_tokens = token_compression.create_compressed_df(schema_check)?;
+            EncodedColumns::new(&[("erc20", "dictionary")], compressed_tokens)
+        },
+    };
+
+    let mut address_compression = DictionaryCompressedAddressSeries::new();
+    if let Some(streams) = base {
+        address_compression.load(streams)?;
+    }
+    let compressed_addresses = address_compression.create_compressed_df(schema_check)?;
+    let codec = if compressed_addresses.len() == 1 { "raw" } else { "dictionary" };
+    encoded.extend(EncodedColumns::new(&[("from_address", codec), ("to_address", codec)], compressed_addresses));
+    Ok(encoded)
+}
+
+/// erc20, from and to through one shared address vocabulary; each column decodes
+/// from its id stream and the vocabulary. A new archive starts the vocabulary
+/// from `registry` addresses when given (`registry` codec).
+fn encode_address_vocabulary(schema_check: &DataFrame, base: Option<&Streams>, registry: Option<&[String]>) -> Result<EncodedColumns> {
+    let mut vocabulary_compression = match registry {
+        Some(addresses) => SharedVocabularyCompressedAddressSeries::with_registry(addresses),
+        None => SharedVocabularyCompressedAddressSeries::new(),
+    };
+    if let Some(streams) = base {
+        vocabulary_compression.load(streams)?;
+    }
+    let dataframes = vocabulary_compression.create_compressed_df(schema_check)?;
+    let codec = if vocabulary_compression.registered.is_some() { "registry" } else { "vocabulary" };
+    let vocabulary = VOCABULARY_COLUMNS.len();
+    Ok(EncodedColumns {
+        columns: VOCABULARY_COLUMNS.iter().enumerate()
+            .map(|(i, (name, _))| (*name, codec, vec![i, vocabulary]))
+            .collect(),
+        dataframes,
+    })
+}
 
 pub struct Transfer {
     pub dataframes: Vec<DataFrame>,            // vec of compressesd dataframes
     pub compressed_df: DataFrame,              // dataset after stacking 
     pub output_filepath: PathBuf,              // filepath for wrting compressed file
+    pub manifest: Manifest,                    // archive layout; codecs and stream lengths
+    pub options: CompressOptions,              // compression options, eg: entropy coding
+    pub output: OutputOptions,                 // where archives are written: directory, naming, overwrite policy
+}
+
+impl Default for Transfer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Transfer {
@@ -34,125 +143,622 @@ impl Transfer {
             dataframes: Vec::new(),                  // dataframe after schema check
             compressed_df: DataFrame::default(),     // compresed dataframe; pre file write
             output_filepath: PathBuf::new(),         // output filepath; 
+            manifest: Manifest::new("transfers"),    // manifest; written alongside the streams
+            options: CompressOptions::new(),         // compression options
+            output: OutputOptions::new(),            // output options
         }
     }
 
-    /// Set new filepath inplace with prefix "BLADE_". Store non-compressed data where you want output.
-    pub fn _update_path(&mut self, filepath: &PathBuf) -> Result<()> {
-        let mut path = PathBuf::from(filepath);
-        let filename = path.file_name().unwrap().to_string_lossy();
-        let amended_filename = format!("BLADE_{}", filename);
-        path.set_file_name(amended_filename);
-        self.output_filepath = path;
-        Ok(())
+    /// Write the compressed dataframes and manifest to `output_filepath`.
+    pub fn write_archive(&mut self) -> Result<()> {
+        let dataframes = self.archive_dataframes()?;
+        parquet_writer(self.output_filepath.clone(), dataframes)?;
+        self.register_addresses()
     }
 
+    /// Write the compressed dataframes and manifest as a BLADE archive into
+    /// `sink`, eg: an in-memory buffer or a network stream.
+    pub fn write_archive_to<W: Write>(&mut self, sink: W) -> Result<()> {
+        let dataframes = self.archive_dataframes()?;
+        parquet_sink_writer(sink, dataframes)?;
+        self.register_addresses()
+    }
+
+    /// Stream dataframes and manifest as written to the archive.
+    fn archive_dataframes(&mut self) -> Result<Vec<DataFrame>> {
+        let mut dataframes = self.entropy_code_streams()?;
+        dataframes.push(self.manifest.to_dataframe()?);
 
-    pub fn write_parquet(&mut self, filepath: &PathBuf) -> Result<()> {
+        // streams are sized alone; keep their coding only if the whole archive shrinks
+        if self.manifest.streams.iter().any(|stream| !stream.encoding.is_plain()) {
+            let mut plain_manifest = self.manifest.clone();
+            for stream in plain_manifest.streams.iter_mut() {
+                stream.encoding = StreamEncoding::Plain;
+            }
+            let mut plain = self.dataframes.clone();
+            plain.push(plain_manifest.to_dataframe()?);
+            if parquet_size(&plain)? <= parquet_size(&dataframes)? {
+                self.manifest = plain_manifest;
+                dataframes = plain;
+            }
+        }
+        Ok(dataframes)
+    }
 
-        // temporary write, check file creation
-        self._update_path(filepath)?;
-        println!("updating path at: {:?}", self.output_filepath);
-        parquet_writer(self.output_filepath.clone(), self.dataframes.clone())?;
-        
-        if self.output_filepath.exists() {
-            let size = std::fs::metadata(&self.output_filepath)?.len();
-            println!("File created! Size: {} bytes", size);
-        } else {
-            println!("File was NOT created");
+    /// Register the addresses a written archive introduced, for the archives after it.
+    fn register_addresses(&self) -> Result<()> {
+        if let (Some(path), Some(_)) = (&self.options.address_registry, &self.manifest.address_registry) {
+            let vocabulary = self.dataframes.iter().find_map(|df| df.column("address_vocabulary").ok());
+            if let Some(vocabulary) = vocabulary {
+                let mut registry = AddressRegistry::open(path)?;
+                registry.register(vocabulary.str()?.into_no_null_iter());
+                registry.save()?;
+            }
         }
         Ok(())
     }
 
+    /// Apply the configured entropy coding to every stream of u32 values without
+    /// nulls, when it writes smaller than the plain stream padded to the archive height. Coded streams are
+    /// stored as u32 words and their encoding recorded in the manifest.
+    fn entropy_code_streams(&mut self) -> Result<Vec<DataFrame>> {
+        // sizes are compared as written: padded with nulls to the archive height
+        let height = self.dataframes.iter().map(|df| df.height()).max().unwrap_or(0);
+        let padded_size = |column: &Column| -> Result<usize> {
+            let padded = column.as_materialized_series().extend_constant(AnyValue::Null, height - column.len())?;
+            parquet_size(&[DataFrame::new(vec![padded.into()])?])
+        };
+
+        let mut dataframes: Vec<DataFrame> = Vec::new();
+        for df in self.dataframes.iter() {
+            for column in df.get_columns() {
+                let mut encoding = StreamEncoding::Plain;
+                let mut stored = column.clone();
+                if self.options.entropy_coding == StreamEncoding::Huffman
+                    && column.dtype() == &DataType::UInt32
+                    && column.null_count() == 0
+                    && !column.is_empty()
+                {
+                    let values: Vec<u32> = column.u32()?.into_no_null_iter().collect();
+                    let coded = Column::new(column.name().clone(), huffman::encode(&values));
+                    if coded.len() <= height && padded_size(&coded)? < padded_size(column)? {
+                        encoding = StreamEncoding::Huffman;
+                        stored = coded;
+                    }
+                }
+                if let Some(entry) = self.manifest.stream_mut(column.name()) {
+                    entry.encoding = encoding;
+                }
+                dataframes.push(DataFrame::new(vec![stored])?);
+            }
+        }
+        Ok(dataframes)
+    }
+
     /// Compress iteratively goes through parquet file columns, applying specific
-    /// compression algorithms to each, to maximize compression ratios.
+    /// compression algorithms to each, to maximize compression ratios. The archive
+    /// is written where the output options place it.
     pub fn compress(&mut self, filepath: &PathBuf) -> Result<()> {
-        
-        let start_time = Instant::now();
-        println!("--------------------------------------------------");
-        println!(">> {} Compression beginning", "[START]".bright_cyan());
-        println!("--------------------------------------------------");
+        self.compress_into(filepath, None)?;
+        Ok(())
+    }
+
+    /// Compress `filepath` as `compress`, with `relative` its directory below the
+    /// batch root. Returns the archive written, `None` when an existing one is skipped.
+    pub fn compress_into(&mut self, filepath: &PathBuf, relative: Option<&Path>) -> Result<Option<PathBuf>> {
+        // Instantiate TransferIngestion (ingestion.rs); validate schema against transfer dataset
+        let mut transfer: TransferIngestion = TransferIngestion::new();
+        let schema_check: DataFrame = transfer.check_schema_validity(filepath)?;
+
+        // resolve the archive path first, so that a skipped archive is never compressed;
+        // cryo filenames carry the block range
+        let blocks = schema_check.column("block_number")?.u32()?;
+        let (start_block, end_block) = match parse_cryo_filename(filepath) {
+            Some(cryo) => (Some(cryo.start_block), Some(cryo.end_block)),
+            None => (blocks.min(), blocks.max()),
+        };
+        let Some(output_filepath) = self.output.archive_path(filepath, relative, start_block, end_block)? else {
+            return Ok(None);
+        };
+
+        // compress columns and write to parquet
+        self.compress_dataframe(&schema_check)?;
+        self.manifest.start_block = start_block;
+        self.manifest.end_block = end_block;
+        if let Some(directory) = output_filepath.parent().filter(|directory| !directory.as_os_str().is_empty()) {
+            std::fs::create_dir_all(directory)?;
+        }
+        self.output_filepath = output_filepath;
+        self.write_archive()?;
+        Ok(Some(self.output_filepath.clone()))
+    }
+
+    /// Compress a transfers parquet file into `sink` as a BLADE archive, eg: stdout.
+    pub fn compress_file_to_writer<W: Write>(&mut self, filepath: &PathBuf, sink: W) -> Result<()> {
+        let mut transfer: TransferIngestion = TransferIngestion::new();
+        let schema_check: DataFrame = transfer.check_schema_validity(filepath)?;
+
+        self.compress_dataframe(&schema_check)?;
+        self.set_cryo_block_range(filepath);
+        self.write_archive_to(sink)
+    }
+
+    /// Compress transfers read from `source`, eg: stdin, into `sink` as a BLADE
+    /// archive. The input format is sniffed from the content: parquet, a JSON
+    /// array, JSON lines, or CSV.
+    pub fn compress_reader<R: Read, W: Write>(&mut self, mut source: R, sink: W) -> Result<()> {
+        let mut bytes: Vec<u8> = Vec::new();
+        source.read_to_end(&mut bytes)?;
+        let format = InputFormat::sniff(&bytes);
+        let dataset = read_transfers(std::io::Cursor::new(bytes), format)?;
+        self.compress_to_writer(&dataset, sink)
+    }
+
+    /// Take the manifest block range from a cryo filename, which also covers
+    /// blocks without transfers at either end.
+    fn set_cryo_block_range(&mut self, filepath: &Path) {
+        if let Some(cryo) = parse_cryo_filename(filepath) {
+            self.manifest.start_block = Some(cryo.start_block);
+            self.manifest.end_block = Some(cryo.end_block);
+        }
+    }
+
+    /// Compress an in-memory transfers dataframe into `sink` as a BLADE archive.
+    pub fn compress_to_writer<W: Write>(&mut self, dataset: &DataFrame, sink: W) -> Result<()> {
+        let mut transfer: TransferIngestion = TransferIngestion::new();
+        transfer.check_dataframe_validity(dataset)?;
+
+        self.compress_dataframe(dataset)?;
+        self.write_archive_to(sink)
+    }
+
+    /// Compress an in-memory transfers dataframe into an in-memory BLADE archive.
+    pub fn compress_to_bytes(&mut self, dataset: &DataFrame) -> Result<Vec<u8>> {
+        let mut buffer: Vec<u8> = Vec::new();
+        self.compress_to_writer(dataset, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Compress typed transfer records into `sink` as a BLADE archive.
+    pub fn compress_records<I: IntoIterator<Item = TransferRecord>, W: Write>(&mut self, records: I, sink: W) -> Result<()> {
+        let dataset = records_to_dataframe(records)?;
+        self.compress_to_writer(&dataset, sink)
+    }
+
+    /// Compress the RecordBatches of an arrow reader, eg: a DataFusion stream,
+    /// into `sink` as a BLADE archive.
+    pub fn compress_record_batch_reader<R: RecordBatchReader, W: Write>(&mut self, batches: R, sink: W) -> Result<()> {
+        let batches: Vec<RecordBatch> = batches.collect::<std::result::Result<_, _>>()
+            .map_err(|e| BladeError::Format(format!("failed reading record batches: {}", e)))?;
+        let dataset = record_batches_to_dataframe(&batches)?;
+        self.compress_to_writer(&dataset, sink)
+    }
+
+    /// Compress RecordBatches holding a transfers dataset and write the archive
+    /// to `output_filepath`, without going through a parquet file first.
+    pub fn compress_record_batches(&mut self, batches: &[RecordBatch], output_filepath: &Path) -> Result<()> {
+        let dataset = record_batches_to_dataframe(batches)?;
+        let mut transfer: TransferIngestion = TransferIngestion::new();
+        transfer.check_dataframe_validity(&dataset)?;
+
+        self.compress_dataframe(&dataset)?;
+        self.output_filepath = output_filepath.to_path_buf();
+        self.write_archive()
+    }
+
+    /// Apply the column specific compression algorithms to a schema checked
+    /// transfers dataframe, collecting the compressed dataframes and manifest.
+    pub fn compress_dataframe(&mut self, schema_check: &DataFrame) -> Result<()> {
+        let blocks = schema_check.column("block_number")?.u32()?;
+        self.manifest.start_block = blocks.min();
+        self.manifest.end_block = blocks.max();
+        self.compress_rows(schema_check, None)
+    }
+
+    /// Compress `schema_check` after the rows of `base`. With row reordering,
+    /// the rows are also compressed in compression order and the smaller
+    /// result is kept; an archive already reordered continues its row order.
+    fn compress_rows(&mut self, schema_check: &DataFrame, base: Option<&Streams>) -> Result<()> {
+        let base_rows = base.map(|streams| streams.rows).unwrap_or(0);
+        let base_order = match base {
+            Some(streams) => RowOrder::load(streams)?,
+            None => None,
+        };
+        if !self.options.reorder_rows && base_order.is_none() {
+            return self.compress_columns(schema_check, base);
+        }
+
+        // candidate orders of the rows: as they came, then compression order
+        let mut orders: Vec<Vec<u32>> = vec![(0..schema_check.height() as u32).collect()];
+        if self.options.reorder_rows {
+            orders.push(compression_order(schema_check)?);
+        }
+        let manifest = self.manifest.clone();
+        let mut best: Option<(usize, Manifest, Vec<DataFrame>)> = None;
+        for (i, order) in orders.iter().enumerate() {
+            self.manifest = manifest.clone();
+            self.dataframes.clear();
+            let rows = match i {
+                0 => schema_check.clone(),
+                _ => schema_check.take(&IdxCa::from_vec("".into(), order.clone()))?,
+            };
+            self.compress_columns(&rows, base)?;
+
+            // rows as they came onto an archive in order need no row order
+            if i > 0 || base_order.is_some() {
+                let mut row_order = base_order.clone().unwrap_or_else(|| RowOrder::identity(base_rows));
+                row_order.extend(&order.iter().map(|row| row + base_rows as u32).collect::<Vec<u32>>());
+                let row_order_df = row_order.create_compressed_df()?;
+                self.manifest.add_streams(std::slice::from_ref(&row_order_df))?;
+                self.dataframes.push(row_order_df);
+            }
+
+            let cost = stream_cost(&self.dataframes)?;
+            if best.as_ref().is_none_or(|(best_cost, _, _)| cost < *best_cost) {
+                best = Some((cost, self.manifest.clone(), std::mem::take(&mut self.dataframes)));
+            }
+        }
+        if let Some((_, manifest, dataframes)) = best {
+            self.manifest = manifest;
+            self.dataframes = dataframes;
+        }
+        Ok(())
+    }
+
+    /// Append a new transfers chunk to an existing BLADE archive in place. RLE
+    /// runs are continued and dictionaries extended from the archived streams,
+    /// and the chunk must start right after the last block of the archive.
+    pub fn append(&mut self, archive_filepath: &PathBuf, filepath: &PathBuf) -> Result<()> {
+        let (manifest, streams) = parquet_reader(archive_filepath)?;
 
         // Instantiate TransferIngestion (ingestion.rs); validate schema against transfer dataset
         let mut transfer: TransferIngestion = TransferIngestion::new();
-        let schema_check: DataFrame = transfer.check_schema_validity(filepath).unwrap();
+        let schema_check: DataFrame = transfer.check_schema_validity(filepath)?;
+
+        // Block range of the chunk; from the cryo filename, else from its rows
+        let blocks = schema_check.column("block_number")?.u32()?;
+        let (first_block, last_block) = match (blocks.min(), blocks.max()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Err(BladeError::Format(format!("chunk {:?} has no blocks to append", filepath))),
+        };
+        let (start_block, end_block) = match parse_cryo_filename(filepath) {
+            Some(cryo) => (cryo.start_block, cryo.end_block),
+            None => (first_block, last_block),
+        };
+        let archive_end = manifest.end_block
+            .ok_or_else(|| BladeError::Format(format!("archive {:?} has no recorded block range", archive_filepath)))?;
+
+        // Enforce contiguous, non-overlapping block ranges
+        if first_block <= archive_end || start_block <= archive_end {
+            return Err(BladeError::Integrity(format!("chunk blocks {}..={} overlap archive ending at block {}", start_block, end_block, archive_end)));
+        }
+        if start_block != archive_end + 1 {
+            return Err(BladeError::Integrity(format!("chunk starting at block {} is not contiguous with archive ending at block {}", start_block, archive_end)));
+        }
+
+        // Continue every codec from the archived streams
+        self.dataframes.clear();
+        self.manifest = Manifest::new(&manifest.dataset);
+        self.manifest.start_block = manifest.start_block;
+        self.manifest.end_block = Some(end_block);
+        self.manifest.address_registry = manifest.address_registry.clone();
+        self.manifest.dictionary_bundle = manifest.dictionary_bundle.clone();
+        self.compress_rows(&schema_check, Some(&streams))?;
+
+        for entry in manifest.columns.iter() {
+            let codec = self.manifest.column(&entry.name).map(|c| c.codec.as_str());
+            if codec != Some(entry.codec.as_str()) {
+                return Err(BladeError::Codec(format!("column `{}` was archived with codec `{}`, cannot append with {:?}", entry.name, entry.codec, codec)));
+            }
+        }
+
+        self.output_filepath = archive_filepath.clone();
+        self.write_archive()
+    }
+
+    /// Run the column codecs over `schema_check`. With `base` streams from an
+    /// existing archive, each codec is loaded first so the chunk is appended.
+    /// Codecs run concurrently; results are collected in a fixed order so the
+    /// archive is byte-identical to a sequential run.
+    fn compress_columns(&mut self, schema_check: &DataFrame, base: Option<&Streams>) -> Result<()> {
 
         let columns: Vec<String> = schema_check.get_column_names().iter().map(|s| s.to_string()).collect();
+        let has = |name: &str| columns.iter().any(|c| c == name);
+        self.manifest.rows = base.map(|streams| streams.rows).unwrap_or(0) + schema_check.height();
+
+        // address registry or trained bundle a new archive starts its dictionaries from
+        let (registry, bundle) = match (base, &self.options.address_registry, &self.options.dictionary_bundle) {
+            (None, Some(_), Some(_)) => return Err(BladeError::Format("an address registry and a dictionary bundle cannot be combined".to_string())),
+            (None, Some(path), None) => (Some(AddressRegistry::open(path)?), None),
+            (None, None, Some(path)) => (None, Some(DictionaryBundle::open(path)?)),
+            _ => (None, None),
+        };
+        let registry_addresses = registry.as_ref().map(|registry| registry.addresses.as_slice())
+            .or(bundle.as_ref().map(|bundle| bundle.addresses.as_slice()));
+        let bundle_amounts = bundle.as_ref().map(|bundle| bundle.amounts.as_slice());
+        let mut steps: Vec<CodecStep> = Vec::new();
 
 
         // 1) block_number: rle compression
-        if columns.contains(&"block_number".to_string()) {
-            let mut block_compression: RLECompressedBlockNumberSeries = RLECompressedBlockNumberSeries::new(); 
-            let compressed_blocks_df = block_compression.create_compressed_df(&schema_check);
-            self.dataframes.push(compressed_blocks_df?);
+        if has("block_number") {
+            steps.push(Box::new(move || {
+                let mut block_compression: RLECompressedBlockNumberSeries = RLECompressedBlockNumberSeries::new(); 
+                if let Some(streams) = base {
+                    block_compression.load(streams)?;
+                }
+                let compressed_blocks_df = block_compression.create_compressed_df(schema_check)?;
+                Ok(EncodedColumns::new(&[("block_number", "rle")], vec![compressed_blocks_df]))
+            }));
         }
 
         // 2) transaction_index: rle compression
-        if columns.contains(&"transaction_index".to_string()) {
-            let mut transaction_compression: RLECompressedTransactionIndexSeries = RLECompressedTransactionIndexSeries::new();
-            let compressed_trans_index = transaction_compression.create_compressed_df(&schema_check);
-            self.dataframes.push(compressed_trans_index?);
+        if has("transaction_index") {
+            steps.push(Box::new(move || {
+                let mut transaction_compression: RLECompressedTransactionIndexSeries = RLECompressedTransactionIndexSeries::new();
+                if let Some(streams) = base {
+                    transaction_compression.load(streams)?;
+                }
+                let compressed_trans_index = transaction_compression.create_compressed_df(schema_check)?;
+                Ok(EncodedColumns::new(&[("transaction_index", "rle")], vec![compressed_trans_index]))
+            }));
         }
 
         // 3) log_index: rle compression
-        if columns.contains(&"log_index".to_string()) {
-            let mut log_index_compression = RLECompressedLogIndexSeries::new();
-            let compressed_log_index = log_index_compression.create_compressed_df(&schema_check);
-            self.dataframes.push(compressed_log_index?);
-        }
-
-        // 4) transaction_hash: dictionary encoding
-        if columns.contains(&"transaction_hash".to_string()) {
-            let mut transaction_hash_compression = DictionaryCompressedTransactionHashSeries::new();
-            let compressed_transaction_df = transaction_hash_compression.create_compressed_df(&schema_check);
-            self.dataframes.push(compressed_transaction_df?);
+        if has("log_index") {
+            steps.push(Box::new(move || {
+                let mut log_index_compression = RLECompressedLogIndexSeries::new();
+                if let Some(streams) = base {
+                    log_index_compression.load(streams)?;
+                }
+                let compressed_log_index = log_index_compression.create_compressed_df(schema_check)?;
+                Ok(EncodedColumns::new(&[("log_index", "rle")], vec![compressed_log_index]))
+            }));
         }
 
-        // 5) erc20: rle compression
-        if columns.contains(&"erc20".to_string()) {
-            let mut token_compression = RLECompressedErc20Series::new();
-            let compressed_tokens_df = token_compression.create_compressed_df(&schema_check);
-            self.dataframes.push(compressed_tokens_df?);
+        // 4) transaction_hash: one hash per (block_number, transaction_index), rebuilt from
+        //    those columns when decoding; hash runs or a hash dictionary when the pair does
+        //    not determine the hash.
+        //    Appends keep the mode of the archive.
+        let keyed_hashes = has("block_number") && has("transaction_index");
+        if has("transaction_hash") {
+            steps.push(Box::new(move || {
+                let keyed = match base {
+                    Some(streams) => streams.contains("tx_hash_keyed_values"),
+                    None => keyed_hashes,
+                };
+                if keyed {
+                    let mut transaction_hash_compression = KeyedCompressedTransactionHashSeries::new();
+                    if let Some(streams) = base {
+                        transaction_hash_compression.load(streams)?;
+                    }
+                    match transaction_hash_compression.create_compressed_df(schema_check)? {
+                        Some(compressed_transaction_df) => return Ok(EncodedColumns::new(&[("transaction_hash", "keyed")], vec![compressed_transaction_df])),
+                        None if base.is_some() => return Err(BladeError::Codec("transaction hashes of the chunk are not determined by (block_number, transaction_index), cannot append to a keyed archive".to_string())),
+                        None => {},
+                    }
+                }
+                // hash runs, or a dictionary of hashes recurring apart; a new archive
+                // takes whichever writes smaller
+                let dictionary = match base {
+                    Some(streams) if streams.contains("tx_hash_dictionary") => {
+                        let mut transaction_hash_compression = DictionaryCompressedTransactionHashSeries::new();
+                        transaction_hash_compression.load(streams)?;
+                        return Ok(EncodedColumns::new(&[("transaction_hash", "dictionary")], transaction_hash_compression.create_compressed_df(schema_check)?));
+                    },
+                    Some(_) => None,
+                    None => Some(DictionaryCompressedTransactionHashSeries::new().create_compressed_df(schema_check)?),
+                };
+                let mut transaction_hash_compression = RLECompressedTransactionHashSeries::new();
+                if let Some(streams) = base {
+                    transaction_hash_compression.load(streams)?;
+                }
+                let runs = vec![transaction_hash_compression.create_compressed_df(schema_check)?];
+                match dictionary {
+                    Some(dictionary) if stream_cost(&dictionary)? < stream_cost(&runs)? => Ok(EncodedColumns::new(&[("transaction_hash", "dictionary")], dictionary)),
+                    _ => Ok(EncodedColumns::new(&[("transaction_hash", "rle")], runs)),
+                }
+            }));
         }
 
-        // n) addresses (to and/or from): dictionary encoding
-        if columns.contains(&"to_address".to_string()) || columns.contains(&"to_address".to_string()) {
-            let mut address_compression = DictionaryCompressedAddressSeries::new();
-            let compressed_addresses = address_compression.create_compressed_df(&schema_check)?;
-            for df in compressed_addresses {
-                println!("df memory: {} bytes, shape: {:?}", df.estimated_size(), df.shape());
-                self.dataframes.push(df);
-            }
+        // 5) erc20, from_address, to_address: one shared address vocabulary, or token
+        //    dictionary plus from / to pair dictionary. A new archive takes whichever
+        //    writes smaller, its vocabulary starting from the address registry when
+        //    one is configured; appends keep the mode and registry of the archive.
+        if has("erc20") && has("from_address") && has("to_address") {
+            steps.push(Box::new(move || {
+                if let Some(streams) = base {
+                    return match streams.contains("address_vocabulary") {
+                        true => encode_address_vocabulary(schema_check, Some(streams), None),
+                        false => encode_token_and_address_pairs(schema_check, Some(streams)),
+                    };
+                }
+                let pairs = encode_token_and_address_pairs(schema_check, None)?;
+                match encode_address_vocabulary(schema_check, None, registry_addresses) {
+                    Ok(vocabulary) if stream_cost(&vocabulary.dataframes)? < stream_cost(&pairs.dataframes)? => Ok(vocabulary),
+                    // erc20 values that are not addresses only fit the token dictionary
+                    Ok(_) | Err(BladeError::MalformedValue { .. }) => Ok(pairs),
+                    Err(e) => Err(e),
+                }
+            }));
         }
 
-        // 9) value_strings: normalization compression 
-        if columns.contains(&"value_strings".to_string()) {
-            let mut value_string_compression: NormalizedCompressedValueStrings = NormalizedCompressedValueStrings::new();
-            let comopressed_value_string = value_string_compression.create_compressed_df(&schema_check);
-            self.dataframes.push(comopressed_value_string?);
+        // 9) value_string: per token dictionary of hot amounts, mantissa / exponent literals.
+        //    Archives stored as literals only keep appending literals.
+        if has("value_string") {
+            steps.push(Box::new(move || {
+                if let Some(streams) = base.filter(|streams| !streams.contains("value_hot_index")) {
+                    let mut value_string_compression = MantissaExponentCompressedValueStrings::new();
+                    value_string_compression.load(streams)?;
+                    let compressed_value_strings = value_string_compression.create_compressed_df(schema_check)?;
+                    return Ok(EncodedColumns::new(&[("value_string", "mantissa_exponent")], compressed_value_strings));
+                }
+                let mut value_string_compression = match bundle_amounts {
+                    Some(amounts) => HotDictionaryCompressedValueStrings::with_bundle(amounts),
+                    None => HotDictionaryCompressedValueStrings::new(),
+                };
+                if let Some(streams) = base {
+                    value_string_compression.load(streams)?;
+                }
+                let compressed_value_strings = value_string_compression.create_compressed_df(schema_check)?;
+                let codec = if compressed_value_strings.len() == 2 { "mantissa_exponent" } else { "hot_dictionary" };
+                Ok(EncodedColumns::new(&[("value_string", codec)], compressed_value_strings))
+            }));
         }
 
         // 11) chain_id: rle compression
-        if columns.contains(&"chain_id".to_string()) {
-            let mut chain_id_compression = RLECompressedChainIdSeries::new();
-            let compressed_chain_id_df = chain_id_compression.create_compressed_df(&schema_check);
-            self.dataframes.push(compressed_chain_id_df?);
+        if has("chain_id") {
+            steps.push(Box::new(move || {
+                let mut chain_id_compression = RLECompressedChainIdSeries::new();
+                if let Some(streams) = base {
+                    chain_id_compression.load(streams)?;
+                }
+                let compressed_chain_id_df = chain_id_compression.create_compressed_df(schema_check)?;
+                Ok(EncodedColumns::new(&[("chain_id", "rle")], vec![compressed_chain_id_df]))
+            }));
         }
 
-        // write to parquet
-        // self.write_parquet(filepath)?;
+        // Run the codecs on the thread pool, then register streams in step order
+        let encoded: Vec<EncodedColumns> = steps.par_iter().map(|step| step()).collect::<Result<_>>()?;
+        for step in encoded {
+            for (name, codec, dataframes) in step.columns {
+                let dataframes: Vec<DataFrame> = dataframes.iter().map(|i| step.dataframes[*i].clone()).collect();
+                self.manifest.add_column(name, codec, &dataframes)?;
+            }
+            self.dataframes.extend(step.dataframes);
+        }
 
-        // End time and output
-        let elapsed_time = start_time.elapsed();
-        println!("--------------------------------------------------");
-        println!("<< {} Completed in {:.2?}", "[END]".bright_cyan(), elapsed_time);
-        println!("--------------------------------------------------");
+        // record the registry version or bundle the dictionaries start from
+        let uses_codec = |name: &str, codec: &str| self.manifest.column(name).is_some_and(|column| column.codec == codec);
+        let registered_addresses = uses_codec("erc20", "registry");
+        let hot_amounts = uses_codec("value_string", "hot_dictionary");
+        if let Some(registry) = &registry {
+            if registered_addresses {
+                self.manifest.address_registry = Some(registry.reference()?);
+            }
+        }
+        if let Some(bundle) = &bundle {
+            if registered_addresses || hot_amounts {
+                self.manifest.dictionary_bundle = Some(bundle.reference()?);
+            }
+        }
 
         Ok(())
     }
 
+    /// Decompress reads a BLADE archive and decodes every stored column back
+    /// into the original transfers dataset.
+    pub fn decompress(&mut self, filepath: &PathBuf) -> Result<DataFrame> {
+        let (manifest, streams) = parquet_reader(filepath)?;
+        self.manifest = manifest;
+        let columns = self.manifest.column_names();
+        self.decompress_columns(&streams, &columns)
+    }
 
-    // pub fn write_parquet(&mut self, filepath: &PathBuf) -> Result<()> {
-    //     Ok(())
-    // }
+    /// Decompress only `columns` of a BLADE archive: only the streams they decode
+    /// from, dependencies of their codecs included, are read, and codecs of other
+    /// columns are never run.
+    pub fn decompress_projection(&mut self, filepath: &PathBuf, columns: &[String]) -> Result<DataFrame> {
+        let (manifest, streams) = parquet_projection_reader(filepath, columns)?;
+        self.manifest = manifest;
+        self.decompress_columns(&streams, columns)
+    }
+
+    /// Decompress a BLADE archive and iterate its rows as typed records. The
+    /// encoded streams are read whole, rows are decoded `CHUNK_ROWS` at a time.
+    pub fn decompress_records(&mut self, filepath: &PathBuf) -> Result<TransferRecordIter> {
+        let columns: Vec<String> = RECORD_COLUMNS.iter().map(|column| column.to_string()).collect();
+        let chunks = self.decompress_chunks(filepath, &columns, CHUNK_ROWS)?;
+        Ok(TransferRecordIter::from_chunks(chunks))
+    }
+
+    /// Decompress `columns` of a BLADE archive into dataframes of up to
+    /// `chunk_rows` rows, decoded as they are iterated; only the streams the
+    /// columns decode from are read.
+    pub fn decompress_chunks(&mut self, filepath: &PathBuf, columns: &[String], chunk_rows: usize) -> Result<ArchiveChunks> {
+        let (manifest, streams) = parquet_projection_reader(filepath, columns)?;
+        self.manifest = manifest.clone();
+        ArchiveChunks::new(manifest, streams, columns, chunk_rows)
+    }
+
+    /// Decompress a BLADE archive held in memory back into the transfers dataset.
+    pub fn decompress_bytes(&mut self, bytes: &[u8]) -> Result<DataFrame> {
+        let (manifest, streams) = archive_from_bytes(bytes)?;
+        self.manifest = manifest;
+        let columns = self.manifest.column_names();
+        self.decompress_columns(&streams, &columns)
+    }
+
+    /// Decompress only `columns` of a BLADE archive held in memory.
+    pub fn decompress_bytes_projection(&mut self, bytes: &[u8], columns: &[String]) -> Result<DataFrame> {
+        let (manifest, streams) = archive_projection_from_bytes(bytes, columns)?;
+        self.manifest = manifest;
+        self.decompress_columns(&streams, columns)
+    }
+
+    /// Decompress a BLADE archive read from `source`, eg: stdin or a network stream.
+    pub fn decompress_reader<R: Read>(&mut self, mut source: R) -> Result<DataFrame> {
+        let mut bytes: Vec<u8> = Vec::new();
+        source.read_to_end(&mut bytes)?;
+        self.decompress_bytes(&bytes)
+    }
+
+    /// Decompress only `columns` of a BLADE archive read from `source`.
+    pub fn decompress_reader_projection<R: Read>(&mut self, mut source: R, columns: &[String]) -> Result<DataFrame> {
+        let mut bytes: Vec<u8> = Vec::new();
+        source.read_to_end(&mut bytes)?;
+        self.decompress_bytes_projection(&bytes, columns)
+    }
+
+    /// Decompress a BLADE archive into an iterator of RecordBatches of at most
+    /// `batch_size` rows, for consumers built on arrow-rs. The archive is decoded
+    /// into memory; batches carry value_binary / value_f64 as compress takes them.
+    pub fn decompress_record_batches(&mut self, filepath: &PathBuf, batch_size: usize) -> Result<TransferRecordBatchReader> {
+        let df = self.decompress(filepath)?;
+        TransferRecordBatchReader::new(df, batch_size)
+    }
+
+    /// Decode only the requested columns from the archive streams; codecs of
+    /// other columns are never run.
+    pub fn decompress_columns(&mut self, streams: &Streams, columns: &[String]) -> Result<DataFrame> {
+        for name in columns {
+            if self.manifest.column(name).is_none() {
+                return Err(BladeError::Schema(format!("column `{}` is not stored in the archive", name)));
+            }
+        }
+
+        let mut decompressed: Vec<Column> = Vec::new();
+        for entry in self.manifest.columns.iter() {
+            if !columns.contains(&entry.name) {
+                continue;
+            }
+            let column = match (entry.name.as_str(), entry.codec.as_str()) {
+                ("block_number", "rle") => RLECompressedBlockNumberSeries::new().decompress(streams)?,
+                ("transaction_index", "rle") => RLECompressedTransactionIndexSeries::new().decompress(streams)?,
+                ("log_index", "rle") => RLECompressedLogIndexSeries::new().decompress(streams)?,
+                ("transaction_hash", "rle") => RLECompressedTransactionHashSeries::new().decompress(streams)?,
+                ("transaction_hash", "dictionary") => DictionaryCompressedTransactionHashSeries::new().decompress(streams)?,
+                ("transaction_hash", "keyed") => KeyedCompressedTransactionHashSeries::new().decompress(streams)?,
+                ("erc20", "rle") => RLECompressedErc20Series::new().decompress(streams)?,
+                ("erc20", "dictionary") => DictionaryCompressedErc20Series::new().decompress(streams)?,
+                ("from_address", "raw" | "dictionary") => DictionaryCompressedAddressSeries::new().decompress(streams)?.0,
+                ("to_address", "raw" | "dictionary") => DictionaryCompressedAddressSeries::new().decompress(streams)?.1,
+                (name @ ("erc20" | "from_address" | "to_address"), "vocabulary" | "registry") => SharedVocabularyCompressedAddressSeries::new().decompress(streams, name)?,
+                ("value_string", "mantissa_exponent") => MantissaExponentCompressedValueStrings::new().decompress(streams)?,
+                ("value_string", "hot_dictionary") => HotDictionaryCompressedValueStrings::new().decompress(streams)?,
+                ("chain_id", "rle") => RLECompressedChainIdSeries::new().decompress(streams)?,
+                (name, codec) => return Err(BladeError::Codec(format!("no decoder for column `{}` with codec `{}`", name, codec))),
+            };
+            if column.len() != streams.rows {
+                return Err(BladeError::Integrity(format!("column `{}` decoded to {} rows, expected {}", entry.name, column.len(), streams.rows)));
+            }
+            decompressed.push(column);
+        }
+
+        // codecs decode in stored order; restore cryo's order of a reordered archive
+        let decompressed = DataFrame::new(decompressed)?;
+        match RowOrder::load(streams)? {
+            Some(row_order) if decompressed.width() > 0 => row_order.restore(&decompressed),
+            _ => Ok(decompressed),
+        }
+    }
 }
@@ -24,9 +24,10 @@
 // ParquetWriter::new(&mut file).finish(&mut combined)?;
 
 
-use std::fs::File;
-use anyhow::Result;
-use std::path::PathBuf;
+use std::fs::{self, File};
+use std::io::Write;
+use crate::error::{BladeError, Result};
+use std::path::{Path, PathBuf};
 use polars::prelude::*;
 
 
@@ -55,13 +56,57 @@ use polars::prelude::*;
 
 // horizontal concatenation
 pub fn parquet_writer(output_filepath: PathBuf, dataframes: Vec<DataFrame>) -> Result<()> {
+    atomic_write(&output_filepath, |file| parquet_sink_writer(file, dataframes))
+}
+
+/// Write the file at `path` atomically: `write` fills a temporary file beside
+/// it, which is synced then renamed over `path`, so a crash never leaves a
+/// half-written file behind.
+pub fn atomic_write(path: &Path, write: impl FnOnce(&mut File) -> Result<()>) -> Result<()> {
+    let filename = path.file_name()
+        .ok_or_else(|| BladeError::Format(format!("{:?} has no file name", path)))?
+        .to_string_lossy();
+    let temporary = path.with_file_name(format!(".{}.{}.tmp", filename, std::process::id()));
+    let written = File::create(&temporary).map_err(BladeError::from).and_then(|mut file| {
+        write(&mut file)?;
+        file.sync_all()?;
+        Ok(())
+    });
+    match written {
+        Ok(()) => Ok(fs::rename(&temporary, path)?),
+        Err(e) => {
+            let _ = fs::remove_file(&temporary);
+            Err(e)
+        },
+    }
+}
+
+/// Horizontal concatenation written to any sink, eg: an in-memory buffer.
+pub fn parquet_sink_writer<W: Write>(sink: W, dataframes: Vec<DataFrame>) -> Result<()> {
     if dataframes.is_empty() {
-        panic!("Dataframe is empty, please check input dataset.");
+        return Err(BladeError::Format("no dataframes to write, please check input dataset".to_string()));
     }
-    
-    let df_combined = polars::functions::concat_df_horizontal(&dataframes, true)?;
-    
-    let mut file = File::create(&output_filepath)?;
-    ParquetWriter::new(&mut file).finish(&mut df_combined.clone())?;
+
+    let mut df_combined = polars::functions::concat_df_horizontal(&dataframes, true)?;
+    ParquetWriter::new(sink).finish(&mut df_combined)?;
     Ok(())
 }
+
+/// Approximate archive cost of one more stream: its manifest entry (written with
+/// the manifest statistics) and parquet column metadata.
+pub const STREAM_OVERHEAD: usize = 512;
+
+/// Size in bytes of `dataframes` written by `parquet_writer`, used to pick stream encodings.
+pub fn parquet_size(dataframes: &[DataFrame]) -> Result<usize> {
+    let mut df_combined = polars::functions::concat_df_horizontal(dataframes, true)?;
+    let mut buffer: Vec<u8> = Vec::new();
+    ParquetWriter::new(&mut buffer).finish(&mut df_combined)?;
+    Ok(buffer.len())
+}
+
+/// Estimated archive cost of the streams in `dataframes`, used to pick between
+/// codec modes that write a different number of streams.
+pub fn stream_cost(dataframes: &[DataFrame]) -> Result<usize> {
+    let streams: usize = dataframes.iter().map(|df| df.width()).sum();
+    Ok(parquet_size(dataframes)? + streams * STREAM_OVERHEAD)
+}
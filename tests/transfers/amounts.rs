@@ -0,0 +1,63 @@
+// Amounts split into a power of ten and a mantissa, and looked up in a
+// dictionary of each token's frequent amounts.
+
+use blade::transfers::compression::{
+    HotDictionaryCompressedValueStrings, MantissaExponentCompressedValueStrings, RLECompressedErc20Series,
+};
+use crate::common::{streams, transfers};
+use polars::prelude::*;
+
+#[test]
+fn mantissa_exponent_round_trip() {
+    let dataset = df!("value_string" => [
+        Some("0"), Some("1000000"), None, Some("123456789012345678901234567890000"), Some("18446744073709551615"), Some("7"),
+    ]).unwrap();
+    let encoded = streams(dataset.height(), &MantissaExponentCompressedValueStrings::new().create_compressed_df(&dataset).unwrap());
+    assert_eq!(encoded.get("value_wide_mantissas").unwrap().len(), 1);
+    let decoded = MantissaExponentCompressedValueStrings::new().decompress(&encoded).unwrap();
+    assert!(decoded.equals_missing(dataset.column("value_string").unwrap()));
+}
+
+#[test]
+fn mantissa_exponent_rejects_non_canonical_amounts() {
+    for value in ["007", "", "1e18", "-5", "0x10"] {
+        let dataset = df!("value_string" => [value; 3]).unwrap();
+        assert!(MantissaExponentCompressedValueStrings::new().create_compressed_df(&dataset).is_err(), "`{}` accepted", value);
+    }
+}
+
+#[test]
+fn hot_amount_dictionary_round_trip() {
+    let dataset = transfers(1_000, 1_300, false);
+    let rows = dataset.height();
+    let tokens = RLECompressedErc20Series::new().create_compressed_df(&dataset).unwrap();
+
+    // the dictionary streams themselves, whichever a new archive would keep
+    let mut values = HotDictionaryCompressedValueStrings::new();
+    values.compress(&dataset).unwrap();
+    assert!(values.index.iter().any(Option::is_some));
+    let mut encoded = values.dataframes().unwrap();
+    encoded.push(tokens.clone());
+    let decoded = HotDictionaryCompressedValueStrings::new().decompress(&streams(rows, &encoded)).unwrap();
+    assert!(decoded.equals_missing(dataset.column("value_string").unwrap()));
+
+    // the literal-only candidate of a new archive decodes to the same values
+    let chosen = HotDictionaryCompressedValueStrings::new().create_compressed_df(&dataset).unwrap();
+    let mut encoded = chosen.clone();
+    encoded.push(tokens);
+    let encoded = streams(rows, &encoded);
+    let decoded = match encoded.contains("value_hot_index") {
+        true => HotDictionaryCompressedValueStrings::new().decompress(&encoded).unwrap(),
+        false => MantissaExponentCompressedValueStrings::new().decompress(&encoded).unwrap(),
+    };
+    assert!(decoded.equals_missing(dataset.column("value_string").unwrap()));
+}
+
+#[test]
+fn hot_amount_dictionary_rejects_non_canonical_amounts() {
+    // hot amounts are checked as well as literals
+    for value in ["007", "", "1e18", "-5", "0x10"] {
+        let dataset = df!("erc20" => ["0xaa"; 3], "value_string" => [value; 3]).unwrap();
+        assert!(HotDictionaryCompressedValueStrings::new().create_compressed_df(&dataset).is_err(), "`{}` accepted", value);
+    }
+}
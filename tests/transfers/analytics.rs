@@ -0,0 +1,109 @@
+// Questions answered on the encoded streams match the decoded transfers.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use blade::error::BladeError;
+use blade::transfers::analytics::TransferAnalytics;
+use blade::transfers::options::CompressOptions;
+use crate::common::{archive, decode, scratch, transfers};
+use polars::prelude::*;
+
+/// Transfers of blocks `start..=end` whose receiver and token follow from the
+/// sender, so that pairs recur and take a pair dictionary rather than a vocabulary.
+fn paired_transfers(start: u32, end: u32) -> DataFrame {
+    let mut dataset = transfers(start, end, false);
+    let address = |id: u64| format!("0x{:040x}", 0xbeef_0000_0000u64.wrapping_mul(id + 1));
+    let senders: Vec<u64> = dataset.column("from_address").unwrap().str().unwrap().into_no_null_iter()
+        .map(|sender| u64::from_str_radix(&sender[26..], 16).unwrap() % 300)
+        .collect();
+    let tokens: Vec<String> = senders.iter().map(|sender| address(1_000 + sender % 12)).collect();
+    let receivers: Vec<String> = senders.iter().map(|sender| address(500 + (sender * 7 + 3) % 300)).collect();
+    let senders: Vec<String> = senders.iter().map(|sender| address(*sender)).collect();
+    dataset.with_column(Column::new("erc20".into(), tokens)).unwrap();
+    dataset.with_column(Column::new("from_address".into(), senders)).unwrap();
+    dataset.with_column(Column::new("to_address".into(), receivers)).unwrap();
+    dataset
+}
+
+#[test]
+fn analytics_match_the_decoded_transfers() {
+    let directory = scratch("analytics");
+    let mut options = CompressOptions::new();
+    let plain = archive(&directory, &transfers(1_000, 1_999, false), 1_000, 1_999, &options);
+    let paired = archive(&directory, &paired_transfers(3_000, 3_999), 3_000, 3_999, &options);
+    let raw = archive(&directory, &paired_transfers(4_000, 4_019), 4_000, 4_019, &options);
+    options.address_registry = Some(directory.join("addresses.registry"));
+    let registered = archive(&directory, &transfers(2_000, 2_999, false), 2_000, 2_999, &options);
+
+    for filepath in [plain, paired, raw, registered] {
+        let decoded = decode(&filepath);
+        let analytics = TransferAnalytics::open(&filepath).unwrap();
+        let blocks: Vec<Option<u32>> = decoded.column("block_number").unwrap().u32().unwrap().iter().collect();
+        let tokens: Vec<Option<&str>> = decoded.column("erc20").unwrap().str().unwrap().iter().collect();
+        let senders: Vec<Option<&str>> = decoded.column("from_address").unwrap().str().unwrap().iter().collect();
+        let receivers: Vec<Option<&str>> = decoded.column("to_address").unwrap().str().unwrap().iter().collect();
+
+        let mut per_block: HashMap<Option<u32>, u64> = HashMap::new();
+        blocks.iter().for_each(|block| *per_block.entry(*block).or_insert(0) += 1);
+        let answered = analytics.transfers_per_block().unwrap();
+        let answered: HashMap<Option<u32>, u64> = answered.column("block_number").unwrap().u32().unwrap().iter()
+            .zip(answered.column("transfers").unwrap().u64().unwrap().into_no_null_iter())
+            .collect();
+        assert_eq!(answered, per_block);
+
+        let mut per_token: HashMap<Option<String>, u64> = HashMap::new();
+        tokens.iter().for_each(|token| *per_token.entry(token.map(str::to_string)).or_insert(0) += 1);
+        let answered = analytics.transfers_per_token().unwrap();
+        let counts: Vec<u64> = answered.column("transfers").unwrap().u64().unwrap().into_no_null_iter().collect();
+        assert!(counts.windows(2).all(|pair| pair[0] >= pair[1]), "tokens are not ordered by transfers");
+        let answered: HashMap<Option<String>, u64> = answered.column("erc20").unwrap().str().unwrap().iter()
+            .map(|token| token.map(str::to_string))
+            .zip(counts)
+            .collect();
+        assert_eq!(answered, per_token);
+
+        let mut per_pair: HashMap<(Option<&str>, Option<&str>), u64> = HashMap::new();
+        senders.iter().zip(receivers.iter()).for_each(|(from, to)| *per_pair.entry((*from, *to)).or_insert(0) += 1);
+        let top = analytics.top_address_pairs(5).unwrap();
+        let froms = top.column("from_address").unwrap().str().unwrap();
+        let tos = top.column("to_address").unwrap().str().unwrap();
+        let counts = top.column("transfers").unwrap().u64().unwrap();
+        let mut expected_counts: Vec<u64> = per_pair.values().copied().collect();
+        expected_counts.sort_unstable_by(|a, b| b.cmp(a));
+        assert_eq!(counts.into_no_null_iter().collect::<Vec<u64>>(), expected_counts[..5]);
+        for ((from, to), count) in froms.iter().zip(tos.iter()).zip(counts.into_no_null_iter()) {
+            assert_eq!(per_pair[&(from, to)], count);
+        }
+
+        // the middle half of the archive's blocks
+        let (first, last) = (blocks.iter().flatten().min().unwrap(), blocks.iter().flatten().max().unwrap());
+        let (start, end) = (first + (last - first) / 4, last - (last - first) / 4);
+        let expected: HashSet<&str> = blocks.iter().zip(senders.iter())
+            .filter(|(block, _)| block.is_some_and(|block| (start..=end).contains(&block)))
+            .filter_map(|(_, sender)| *sender)
+            .collect();
+        assert_eq!(analytics.unique_senders(start, end).unwrap(), expected.len() as u64);
+    }
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn analytics_refuse_streams_shorter_than_the_rows() {
+    let directory = scratch("analytics_lengths");
+    let options = CompressOptions::new();
+    let plain = archive(&directory, &transfers(1_000, 1_999, false), 1_000, 1_999, &options);
+    let paired = archive(&directory, &paired_transfers(3_000, 3_999), 3_000, 3_999, &options);
+    let raw = archive(&directory, &paired_transfers(4_000, 4_019), 4_000, 4_019, &options);
+
+    for filepath in [plain, paired, raw] {
+        let mut analytics = TransferAnalytics::open(&filepath).unwrap();
+        let stream = ["address_pairs", "address_index", "from_address_ids"].into_iter()
+            .find(|stream| analytics.streams.contains(stream))
+            .unwrap();
+        let shortened = analytics.streams.get(stream).unwrap().head(Some(analytics.streams.rows - 1));
+        analytics.streams.insert(shortened);
+        assert!(matches!(analytics.unique_senders(0, u32::MAX), Err(BladeError::Integrity(_))), "stream `{}`", stream);
+        assert!(matches!(analytics.top_address_pairs(5), Err(BladeError::Integrity(_))), "stream `{}`", stream);
+    }
+    fs::remove_dir_all(directory).unwrap();
+}
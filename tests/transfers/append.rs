@@ -0,0 +1,26 @@
+// Appending the next contiguous block range to an archive in place.
+
+use std::fs;
+use blade::transfers::options::CompressOptions;
+use blade::transfers::transfers::Transfer;
+use crate::common::{archive, assert_transfers_eq, concat, decode, scratch, transfers, write_chunk};
+
+#[test]
+fn append_continues_the_archive() {
+    let directory = scratch("append");
+    let first = transfers(1_000, 1_999, false);
+    let second = transfers(2_000, 2_999, false);
+    let archive = archive(&directory, &first, 1_000, 1_999, &CompressOptions::new());
+    let chunk = write_chunk(&directory, &second, 2_000, 2_999);
+
+    Transfer::new().append(&archive, &chunk).unwrap();
+    assert_transfers_eq(&decode(&archive), &concat(&[&first, &second]));
+
+    // the next chunk must start right after the archive, without overlapping it
+    let gap = write_chunk(&directory, &transfers(3_500, 3_999, false), 3_500, 3_999);
+    assert!(Transfer::new().append(&archive, &gap).is_err());
+    let overlap = write_chunk(&directory, &transfers(2_500, 3_499, false), 2_500, 3_499);
+    assert!(Transfer::new().append(&archive, &overlap).is_err());
+    assert_transfers_eq(&decode(&archive), &concat(&[&first, &second]));
+    fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,29 @@
+// Dictionary bundles trained on sample files and compressed against.
+
+use std::fs;
+use blade::transfers::bundle::DictionaryBundle;
+use blade::transfers::options::CompressOptions;
+use crate::common::{archive, assert_transfers_eq, decode, scratch, transfers, write_chunk};
+
+#[test]
+fn bundle_trains_and_archives_open_against_it() {
+    let directory = scratch("bundle");
+    let samples = vec![
+        write_chunk(&directory, &transfers(1_000, 1_999, false), 1_000, 1_999),
+        write_chunk(&directory, &transfers(2_000, 2_999, false), 2_000, 2_999),
+    ];
+    let path = directory.join("transfers.bundle");
+    let bundle = DictionaryBundle::train(&path, &samples).unwrap();
+    assert!(!bundle.addresses.is_empty() && !bundle.amounts.is_empty());
+    bundle.save().unwrap();
+
+    let opened = DictionaryBundle::open(&path).unwrap();
+    assert_eq!(opened.addresses, bundle.addresses);
+    assert_eq!(opened.amounts, bundle.amounts);
+
+    let mut options = CompressOptions::new();
+    options.dictionary_bundle = Some(path);
+    let archive = archive(&directory, &transfers(3_000, 3_999, false), 3_000, 3_999, &options);
+    assert_transfers_eq(&decode(&archive), &transfers(3_000, 3_999, false));
+    fs::remove_dir_all(directory).unwrap();
+}
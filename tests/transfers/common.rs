@@ -0,0 +1,154 @@
+// Shared fixtures: deterministic transfers datasets in cryo's schema, scratch
+// directories, archives written from them, and stream helpers for codec round trips.
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use blade::transfers::compression::Streams;
+use blade::transfers::options::CompressOptions;
+use blade::transfers::record::with_derived_values;
+use blade::transfers::transfers::Transfer;
+use polars::prelude::*;
+
+/// xorshift64, so that datasets are the same on every run.
+fn next(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+/// Transfers of blocks `start..=end` in cryo's schema. A few tokens and senders
+/// dominate and amounts recur within tokens, as on chain; some amounts overflow
+/// u64. With `null_blocks`, every 13th row has no block_number, so hashes cannot
+/// be keyed by (block_number, transaction_index).
+pub fn transfers(start: u32, end: u32, null_blocks: bool) -> DataFrame {
+    let mut state = 0x9e37_79b9_7f4a_7c15 ^ start as u64;
+    let tokens: Vec<String> = (0..12u64).map(|i| format!("0x{:040x}", 0xa0b8_6991_c621_8b36u64.wrapping_mul(i + 1))).collect();
+    let addresses: Vec<String> = (0..400u64).map(|i| format!("0x{:040x}", 0x1111_2222_3333u64 * (i + 7))).collect();
+
+    let (mut blocks, mut transaction_indexes, mut log_indexes, mut hashes) = (vec![], vec![], vec![], vec![]);
+    let (mut erc20, mut from_addresses, mut to_addresses, mut values) = (vec![], vec![], vec![], vec![]);
+    for block in start..=end {
+        if next(&mut state).is_multiple_of(5) {
+            continue;
+        }
+        let mut log_index = 0u32;
+        for transaction_index in 0..(1 + next(&mut state) % 4) as u32 {
+            let hash = format!("0x{:016x}{:016x}{:016x}{:016x}", next(&mut state), next(&mut state), next(&mut state), next(&mut state));
+            for _ in 0..1 + next(&mut state) % 3 {
+                log_index += 1 + (next(&mut state) % 2) as u32;
+                let token = match next(&mut state) % 4 {
+                    0 => (next(&mut state) % 12) as usize,
+                    _ => (next(&mut state) % 2) as usize,
+                };
+                let from = match next(&mut state) % 2 {
+                    0 => (next(&mut state) % 10) as usize,
+                    _ => (next(&mut state) % 400) as usize,
+                };
+                let value = match next(&mut state) % 6 {
+                    0 => format!("{}000000000000000000", 1 + next(&mut state) % 50),
+                    1 => format!("{}{:019}", next(&mut state) % 1000 + 1, next(&mut state) % 10u64.pow(19)),
+                    2 => "1000000".to_string(),
+                    3 => "0".to_string(),
+                    _ => (next(&mut state) % 1_000_000_000).to_string(),
+                };
+                let row = blocks.len();
+                blocks.push(Some(block).filter(|_| !null_blocks || row % 13 != 7));
+                transaction_indexes.push(transaction_index);
+                log_indexes.push(log_index);
+                hashes.push(hash.clone());
+                erc20.push(tokens[token].clone());
+                from_addresses.push(addresses[from].clone());
+                to_addresses.push(addresses[(next(&mut state) % 400) as usize].clone());
+                values.push(value);
+            }
+        }
+    }
+    let chain_ids: Vec<u64> = vec![1; blocks.len()];
+    let df = df!(
+        "block_number" => blocks,
+        "transaction_index" => transaction_indexes,
+        "log_index" => log_indexes,
+        "transaction_hash" => hashes,
+        "erc20" => erc20,
+        "from_address" => from_addresses,
+        "to_address" => to_addresses,
+        "value_string" => values,
+        "chain_id" => chain_ids,
+    ).unwrap();
+    with_derived_values(&df).unwrap()
+}
+
+/// `dataset` with nulls in every stored column, each column on rows of its own;
+/// value_binary / value_f64 follow value_string.
+pub fn with_nulls(dataset: &DataFrame) -> DataFrame {
+    let stored = dataset.drop_many(["value_binary", "value_f64"]);
+    let mut columns: Vec<Column> = Vec::with_capacity(stored.width());
+    for (i, column) in stored.get_columns().iter().enumerate() {
+        let keep: BooleanChunked = (0..stored.height()).map(|row| row % 11 != i).collect();
+        let nulls = Series::full_null(column.name().clone(), stored.height(), column.dtype());
+        columns.push(column.as_materialized_series().zip_with(&keep, &nulls).unwrap().into());
+    }
+    with_derived_values(&DataFrame::new(columns).unwrap()).unwrap()
+}
+
+/// Empty scratch directory, unique to the test `name`.
+pub fn scratch(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("blade_test_{}_{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+/// Write `dataset` as the cryo chunk of blocks `start..=end` in `directory`.
+pub fn write_chunk(directory: &Path, dataset: &DataFrame, start: u32, end: u32) -> PathBuf {
+    let filepath = directory.join(format!("ethereum__erc20_transfers__{}_to_{}.parquet", start, end));
+    let mut dataset = dataset.clone();
+    ParquetWriter::new(File::create(&filepath).unwrap()).finish(&mut dataset).unwrap();
+    filepath
+}
+
+/// Write `dataset` as the cryo chunk of blocks `start..=end` in `directory` and
+/// compress it beside the chunk; returns the archive.
+pub fn archive(directory: &Path, dataset: &DataFrame, start: u32, end: u32, options: &CompressOptions) -> PathBuf {
+    let chunk = write_chunk(directory, dataset, start, end);
+    let mut transfer = Transfer::new();
+    transfer.options = options.clone();
+    transfer.compress(&chunk).unwrap();
+    transfer.output_filepath
+}
+
+/// Every column of an archive, decoded.
+pub fn decode(filepath: &PathBuf) -> DataFrame {
+    Transfer::new().decompress(filepath).unwrap()
+}
+
+/// Streams of a codec's compressed dataframes, each column at its own length.
+pub fn streams(rows: usize, dataframes: &[DataFrame]) -> Streams {
+    let mut streams = Streams::new(rows);
+    for df in dataframes {
+        for column in df.get_columns() {
+            streams.insert(column.clone());
+        }
+    }
+    streams
+}
+
+/// Decoded transfers equal the source on every column they carry.
+pub fn assert_transfers_eq(decoded: &DataFrame, source: &DataFrame) {
+    let source = source.select(decoded.get_column_names_owned()).unwrap();
+    assert_eq!(decoded.height(), source.height());
+    for (decoded, source) in decoded.get_columns().iter().zip(source.get_columns()) {
+        assert!(decoded.equals_missing(source), "column `{}` differs", decoded.name());
+    }
+}
+
+/// Stack datasets in order.
+pub fn concat(datasets: &[&DataFrame]) -> DataFrame {
+    let mut stacked = datasets[0].clone();
+    for dataset in &datasets[1..] {
+        stacked.vstack_mut(dataset).unwrap();
+    }
+    stacked.align_chunks();
+    stacked
+}
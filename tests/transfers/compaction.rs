@@ -0,0 +1,69 @@
+// Merging archives of adjacent block ranges, and compacting a directory of them.
+
+use std::fs;
+use std::path::PathBuf;
+use blade::transfers::compaction::{compact_directory, merge_archives};
+use blade::transfers::options::CompressOptions;
+use blade::transfers::output::{OutputOptions, OverwritePolicy};
+use crate::common::{archive, assert_transfers_eq, concat, decode, scratch, transfers};
+
+#[test]
+fn merge_stacks_adjacent_archives() {
+    let directory = scratch("merge");
+    let first = archive(&directory, &transfers(1_000, 1_999, false), 1_000, 1_999, &CompressOptions::new());
+    let second = archive(&directory, &transfers(2_000, 2_999, false), 2_000, 2_999, &CompressOptions::new());
+    let merged = directory.join("merged.parquet");
+
+    // inputs in any order; ranges are sorted before stacking
+    let manifest = merge_archives(&[second.clone(), first.clone()], &merged, &CompressOptions::new()).unwrap();
+    assert_eq!((manifest.start_block, manifest.end_block), (Some(1_000), Some(2_999)));
+    assert_transfers_eq(&decode(&merged), &concat(&[&transfers(1_000, 1_999, false), &transfers(2_000, 2_999, false)]));
+
+    // overlapping or gapped ranges are refused
+    assert!(merge_archives(&[first.clone(), merged.clone()], &directory.join("overlap.parquet"), &CompressOptions::new()).is_err());
+    let third = archive(&directory, &transfers(3_500, 3_999, false), 3_500, 3_999, &CompressOptions::new());
+    assert!(merge_archives(&[first, third], &directory.join("gap.parquet"), &CompressOptions::new()).is_err());
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn compaction_merges_runs_of_archives() {
+    let directory = scratch("compact");
+    let archives: Vec<PathBuf> = [(1_000, 1_999), (2_000, 2_999), (3_000, 3_999)].iter()
+        .map(|(start, end)| archive(&directory, &transfers(*start, *end, false), *start, *end, &CompressOptions::new()))
+        .collect();
+
+    let written = compact_directory(&directory, usize::MAX, &CompressOptions::new(), &OutputOptions::new()).unwrap();
+    assert_eq!(written, vec![directory.join("BLADE_ethereum__erc20_transfers__1000_to_3999.parquet")]);
+    assert!(archives.iter().all(|archive| !archive.exists()));
+    let expected = concat(&[&transfers(1_000, 1_999, false), &transfers(2_000, 2_999, false), &transfers(3_000, 3_999, false)]);
+    assert_transfers_eq(&decode(&written[0]), &expected);
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn compaction_follows_the_output_options() {
+    let directory = scratch("compact_output");
+    let archives: Vec<PathBuf> = [(1_000, 1_999), (2_000, 2_999)].iter()
+        .map(|(start, end)| archive(&directory, &transfers(*start, *end, false), *start, *end, &CompressOptions::new()))
+        .collect();
+    let mut output = OutputOptions::new();
+    output.directory = Some(directory.join("compacted"));
+    output.naming = "{chain}__{start}_to_{end}.blade".to_string();
+    let target = directory.join("compacted/ethereum__1000_to_2999.blade");
+
+    // an existing archive fails by default and is skipped when asked to, the inputs kept
+    fs::create_dir_all(target.parent().unwrap()).unwrap();
+    fs::write(&target, b"existing").unwrap();
+    assert!(compact_directory(&directory, usize::MAX, &CompressOptions::new(), &output).is_err());
+    output.overwrite = OverwritePolicy::Skip;
+    assert!(compact_directory(&directory, usize::MAX, &CompressOptions::new(), &output).unwrap().is_empty());
+    assert!(archives.iter().all(|archive| archive.exists()));
+    assert_eq!(fs::read(&target).unwrap(), b"existing");
+
+    output.overwrite = OverwritePolicy::Replace;
+    assert_eq!(compact_directory(&directory, usize::MAX, &CompressOptions::new(), &output).unwrap(), vec![target.clone()]);
+    assert!(archives.iter().all(|archive| !archive.exists()));
+    assert_transfers_eq(&decode(&target), &concat(&[&transfers(1_000, 1_999, false), &transfers(2_000, 2_999, false)]));
+    fs::remove_dir_all(directory).unwrap();
+}
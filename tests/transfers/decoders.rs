@@ -0,0 +1,68 @@
+// Decoders of every column codec, back to the transfers the archive was written from.
+
+use std::fs;
+use blade::error::BladeError;
+use blade::transfers::compression::{
+    DictionaryCompressedAddressSeries, DictionaryCompressedTransactionHashSeries, KeyedCompressedTransactionHashSeries,
+    RLECompressedTransactionHashSeries,
+};
+use blade::transfers::options::CompressOptions;
+use blade::transfers::transfers::Transfer;
+use crate::common::{archive, assert_transfers_eq, decode, scratch, streams, transfers};
+use polars::prelude::*;
+
+#[test]
+fn archive_decodes_every_column() {
+    let directory = scratch("decoders");
+    let dataset = transfers(1_000, 1_999, false);
+    let archive = archive(&directory, &dataset, 1_000, 1_999, &CompressOptions::new());
+    let decoded = decode(&archive);
+    assert_eq!(decoded.width(), dataset.width() - 2, "every stored column, value_binary / value_f64 aside");
+    assert_transfers_eq(&decoded, &dataset);
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn transaction_hash_runs_and_dictionary_round_trip() {
+    let dataset = transfers(1_000, 1_300, false);
+    let rows = dataset.height();
+
+    let runs = streams(rows, &[RLECompressedTransactionHashSeries::new().create_compressed_df(&dataset).unwrap()]);
+    let decoded = RLECompressedTransactionHashSeries::new().decompress(&runs).unwrap();
+    assert!(decoded.equals_missing(dataset.column("transaction_hash").unwrap()));
+
+    // shuffled rows break the runs; the dictionary still stores each hash once
+    let shuffled = dataset.take(&IdxCa::from_vec("".into(), (0..rows as IdxSize).map(|row| (row * 7919) % rows as IdxSize).collect())).unwrap();
+    let dictionary = streams(rows, &DictionaryCompressedTransactionHashSeries::new().create_compressed_df(&shuffled).unwrap());
+    let distinct = shuffled.column("transaction_hash").unwrap().n_unique().unwrap();
+    assert_eq!(dictionary.get("tx_hash_dictionary").unwrap().len(), distinct);
+    let decoded = DictionaryCompressedTransactionHashSeries::new().decompress(&dictionary).unwrap();
+    assert!(decoded.equals_missing(shuffled.column("transaction_hash").unwrap()));
+}
+
+#[test]
+fn address_pairs_round_trip_as_dictionary_and_raw() {
+    // few distinct pairs over many rows take a dictionary, a short chunk of unique pairs is raw
+    for (dataset, stream) in [(transfers(1_000, 3_000, false), "address_values"), (transfers(1_000, 1_010, false), "address_pairs")] {
+        let encoded = streams(dataset.height(), &DictionaryCompressedAddressSeries::new().create_compressed_df(&dataset).unwrap());
+        assert!(encoded.contains(stream), "expected stream `{}`", stream);
+        let (from, to) = DictionaryCompressedAddressSeries::new().decompress(&encoded).unwrap();
+        assert!(from.equals_missing(dataset.column("from_address").unwrap()));
+        assert!(to.equals_missing(dataset.column("to_address").unwrap()));
+    }
+}
+
+#[test]
+fn transaction_hashes_must_be_lowercase() {
+    // hashes decode as lowercase hex, so an uppercase digit would not round trip
+    let mut dataset = transfers(1_000, 1_100, false);
+    let mut hashes: Vec<String> = dataset.column("transaction_hash").unwrap().str().unwrap().into_no_null_iter().map(str::to_string).collect();
+    hashes[5] = format!("0x{}", hashes[5][2..].to_uppercase());
+    dataset.with_column(Column::new("transaction_hash".into(), hashes)).unwrap();
+
+    let malformed = |result: Result<(), BladeError>| matches!(result, Err(BladeError::MalformedValue { row: 5, .. }));
+    assert!(malformed(RLECompressedTransactionHashSeries::new().create_compressed_df(&dataset).map(drop)));
+    assert!(malformed(DictionaryCompressedTransactionHashSeries::new().create_compressed_df(&dataset).map(drop)));
+    assert!(malformed(KeyedCompressedTransactionHashSeries::new().create_compressed_df(&dataset).map(drop)));
+    assert!(malformed(Transfer::new().compress_to_bytes(&dataset).map(drop)));
+}
@@ -0,0 +1,58 @@
+// Failures surface as BladeError variants naming what went wrong, never as panics.
+
+use blade::error::BladeError;
+use blade::transfers::compression::{RLECompressedBlockNumberSeries, RLECompressedErc20Series, RLECompressedTransactionHashSeries};
+use blade::transfers::transfers::Transfer;
+use crate::common::{streams, transfers};
+use polars::prelude::*;
+
+#[test]
+fn malformed_values_name_their_column_and_row() {
+    let mut dataset = transfers(1_000, 1_100, false);
+    let mut hashes: Vec<String> = dataset.column("transaction_hash").unwrap().str().unwrap().into_no_null_iter().map(str::to_string).collect();
+    hashes[17] = "0xnot-hex".to_string();
+    dataset.with_column(Column::new("transaction_hash".into(), hashes)).unwrap();
+
+    match Transfer::new().compress_to_bytes(&dataset) {
+        Err(BladeError::MalformedValue { column, row, .. }) => assert_eq!((column.as_str(), row), ("transaction_hash", 17)),
+        other => panic!("expected a malformed value, got {:?}", other.map(|bytes| bytes.len())),
+    }
+}
+
+#[test]
+fn schema_and_format_errors() {
+    let dataset = transfers(1_000, 1_100, false).drop("log_index").unwrap();
+    assert!(matches!(Transfer::new().compress_to_bytes(&dataset), Err(BladeError::Schema(_))));
+    assert!(Transfer::new().decompress_bytes(b"not an archive").is_err());
+
+    // a parquet file without a manifest is not an archive
+    let mut plain = transfers(1_000, 1_100, false);
+    let mut bytes: Vec<u8> = Vec::new();
+    ParquetWriter::new(&mut bytes).finish(&mut plain).unwrap();
+    assert!(matches!(Transfer::new().decompress_bytes(&bytes), Err(BladeError::Format(_))));
+}
+
+#[test]
+fn run_lengths_must_cover_the_rows() {
+    let dataset = transfers(1_000, 1_100, false);
+    let rows = dataset.height();
+
+    // a count blown up to u32::MAX, or one run dropped, no longer adds up to the rows
+    let mut blocks = streams(rows, &[RLECompressedBlockNumberSeries::new().create_compressed_df(&dataset).unwrap()]);
+    let mut counts: Vec<u32> = blocks.get("block_counts").unwrap().u32().unwrap().into_no_null_iter().collect();
+    counts[0] = u32::MAX;
+    blocks.insert(Column::new("block_counts".into(), &counts));
+    assert!(matches!(RLECompressedBlockNumberSeries::new().decompress(&blocks), Err(BladeError::Integrity(_))));
+
+    let mut tokens = streams(rows, &[RLECompressedErc20Series::new().create_compressed_df(&dataset).unwrap()]);
+    let counts: Vec<u32> = tokens.get("token_counts").unwrap().u32().unwrap().into_no_null_iter().skip(1).collect();
+    tokens.insert(Column::new("token_counts".into(), &counts));
+    assert!(matches!(RLECompressedErc20Series::new().decompress(&tokens), Err(BladeError::Integrity(_))));
+
+    // hash runs starting past the last row
+    let mut hashes = streams(rows, &[RLECompressedTransactionHashSeries::new().create_compressed_df(&dataset).unwrap()]);
+    let mut starts: Vec<u32> = hashes.get("tx_hash_index").unwrap().u32().unwrap().into_no_null_iter().collect();
+    *starts.last_mut().unwrap() = u32::MAX;
+    hashes.insert(Column::new("tx_hash_index".into(), &starts));
+    assert!(matches!(RLECompressedTransactionHashSeries::new().decompress(&hashes), Err(BladeError::Integrity(_))));
+}
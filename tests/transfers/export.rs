@@ -0,0 +1,60 @@
+// Decoded transfers exported as parquet, CSV, NDJSON and Arrow IPC.
+
+use std::fs::{self, File};
+use blade::transfers::export::{export_transfers, ExportCompression, ExportFormat, ExportOptions};
+use blade::transfers::ingestion::read_transfers_file;
+use blade::transfers::transfers::Transfer;
+use crate::common::{assert_transfers_eq, scratch, transfers};
+use polars::prelude::*;
+
+#[test]
+fn exports_read_back_as_the_transfers() {
+    let directory = scratch("export");
+    let dataset = transfers(1_000, 1_300, false);
+    let decoded = Transfer::new().decompress_bytes(&Transfer::new().compress_to_bytes(&dataset).unwrap()).unwrap();
+
+    for (name, compression) in [("transfers.parquet", Some(ExportCompression::Zstd(Some(9)))), ("transfers.csv", None), ("transfers.ndjson", None)] {
+        let filepath = directory.join(name);
+        let mut options = ExportOptions::new();
+        options.format = ExportFormat::from_path(&filepath);
+        options.compression = compression;
+        export_transfers(File::create(&filepath).unwrap(), &decoded, &options).unwrap();
+        let exported = read_transfers_file(&filepath).unwrap();
+        assert_eq!(exported.get_column_names(), dataset.get_column_names());
+        assert_transfers_eq(&exported, &dataset);
+    }
+
+    let filepath = directory.join("transfers.arrow");
+    let mut options = ExportOptions::new();
+    options.format = Some(ExportFormat::Ipc);
+    options.compression = Some(ExportCompression::Lz4);
+    export_transfers(File::create(&filepath).unwrap(), &decoded, &options).unwrap();
+    assert_transfers_eq(&IpcReader::new(File::open(&filepath).unwrap()).finish().unwrap(), &dataset);
+
+    // IPC takes no gzip, CSV no compression at all
+    options.compression = Some(ExportCompression::Gzip(None));
+    assert!(export_transfers(Vec::new(), &decoded, &options).is_err());
+    options.format = Some(ExportFormat::Csv);
+    assert!(export_transfers(Vec::new(), &decoded, &options).is_err());
+    assert!("zstd:x".parse::<ExportCompression>().is_err());
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn exports_project_columns_in_order() {
+    let dataset = transfers(1_000, 1_300, false);
+    let bytes = Transfer::new().compress_to_bytes(&dataset).unwrap();
+    let mut options = ExportOptions::new();
+    options.columns = Some(vec!["value_f64".to_string(), "erc20".to_string(), "value_string".to_string()]);
+    assert_eq!(options.stored_columns().unwrap(), vec!["value_string".to_string(), "erc20".to_string()]);
+
+    let decoded = Transfer::new().decompress_bytes_projection(&bytes, &options.stored_columns().unwrap()).unwrap();
+    let mut exported: Vec<u8> = Vec::new();
+    export_transfers(&mut exported, &decoded, &options).unwrap();
+    let exported = ParquetReader::new(std::io::Cursor::new(exported)).finish().unwrap();
+    assert_eq!(exported.get_column_names(), ["value_f64", "erc20", "value_string"]);
+    assert_transfers_eq(&exported, &dataset);
+
+    options.columns = Some(vec!["gas_used".to_string()]);
+    assert!(export_transfers(Vec::new(), &decoded, &options).is_err());
+}
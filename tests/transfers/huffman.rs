@@ -0,0 +1,46 @@
+// Canonical Huffman coding of the index and count streams.
+
+use blade::transfers::compression::huffman;
+use blade::transfers::manifest::StreamEncoding;
+use blade::transfers::options::CompressOptions;
+use blade::transfers::transfers::Transfer;
+use crate::common::{assert_transfers_eq, transfers};
+
+#[test]
+fn huffman_round_trip() {
+    let skewed: Vec<u32> = (0..20_000u32).map(|i| if i % 5 == 0 { i % 300 } else { i % 3 }).collect();
+    let words = huffman::encode(&skewed);
+    assert!(words.len() < skewed.len());
+    assert_eq!(huffman::decode(&words, skewed.len()).unwrap(), skewed);
+
+    let single = vec![42u32; 100];
+    assert_eq!(huffman::decode(&huffman::encode(&single), single.len()).unwrap(), single);
+    assert_eq!(huffman::decode(&huffman::encode(&[]), 0).unwrap(), Vec::<u32>::new());
+}
+
+#[test]
+fn huffman_rejects_truncated_and_oversized_input() {
+    let values: Vec<u32> = (0..5_000u32).map(|i| i.wrapping_mul(2_654_435_761) % 37).collect();
+    let words = huffman::encode(&values);
+    for cut in [0, 1, 2, words.len() / 2, words.len() - 1] {
+        assert!(huffman::decode(&words[..cut], values.len()).is_err(), "decoded {} of {} words", cut, words.len());
+    }
+
+    // a symbol count or value count beyond the stream is refused before allocating
+    let mut oversized = words.clone();
+    oversized[0] = u32::MAX;
+    assert!(huffman::decode(&oversized, values.len()).is_err());
+    assert!(huffman::decode(&words, usize::MAX / 2).is_err());
+}
+
+#[test]
+fn archive_round_trips_with_huffman() {
+    let mut options = CompressOptions::new();
+    options.entropy_coding = StreamEncoding::Huffman;
+    let dataset = transfers(1_000, 2_000, false);
+
+    let mut transfer = Transfer::new();
+    transfer.options = options;
+    let bytes = transfer.compress_to_bytes(&dataset).unwrap();
+    assert_transfers_eq(&Transfer::new().decompress_bytes(&bytes).unwrap(), &dataset);
+}
@@ -0,0 +1,30 @@
+// Archives compressed from dataframes held in memory into buffers or sinks,
+// and decoded from byte slices or readers.
+
+use std::fs::{self, File};
+use blade::transfers::transfers::Transfer;
+use crate::common::{assert_transfers_eq, scratch, transfers};
+
+#[test]
+fn archive_round_trips_in_memory() {
+    for null_blocks in [false, true] {
+        let dataset = transfers(1_000, 2_000, null_blocks);
+        let bytes = Transfer::new().compress_to_bytes(&dataset).unwrap();
+        assert_transfers_eq(&Transfer::new().decompress_bytes(&bytes).unwrap(), &dataset);
+    }
+}
+
+#[test]
+fn archive_round_trips_through_writers_and_readers() {
+    let directory = scratch("in_memory");
+    let dataset = transfers(1_000, 2_000, false);
+    let filepath = directory.join("transfers.blade");
+    Transfer::new().compress_to_writer(&dataset, File::create(&filepath).unwrap()).unwrap();
+    assert_eq!(fs::read(&filepath).unwrap(), Transfer::new().compress_to_bytes(&dataset).unwrap());
+
+    let decoded = Transfer::new().decompress_reader(File::open(&filepath).unwrap()).unwrap();
+    assert_transfers_eq(&decoded, &dataset);
+    let projected = Transfer::new().decompress_reader_projection(File::open(&filepath).unwrap(), &["erc20".to_string()]).unwrap();
+    assert_transfers_eq(&projected, &dataset);
+    fs::remove_dir_all(directory).unwrap();
+}
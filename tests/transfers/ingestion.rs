@@ -0,0 +1,53 @@
+// CSV, NDJSON and JSON transfer exports read into the transfers schema.
+
+use std::fs::{self, File};
+use blade::error::BladeError;
+use blade::transfers::ingestion::read_transfers_file;
+use blade::transfers::transfers::Transfer;
+use crate::common::{assert_transfers_eq, decode, scratch, transfers};
+use polars::prelude::*;
+
+#[test]
+fn exports_read_as_transfers() {
+    let directory = scratch("ingestion");
+    let dataset = transfers(1_000, 1_300, false);
+    let mut export = dataset.drop_many(["value_binary", "value_f64"]);
+
+    let csv = directory.join("ethereum__erc20_transfers__1000_to_1300.csv");
+    CsvWriter::new(File::create(&csv).unwrap()).finish(&mut export).unwrap();
+    let ndjson = directory.join("transfers.ndjson");
+    JsonWriter::new(File::create(&ndjson).unwrap()).with_json_format(JsonFormat::JsonLines).finish(&mut export).unwrap();
+    let json = directory.join("transfers.json");
+    JsonWriter::new(File::create(&json).unwrap()).with_json_format(JsonFormat::Json).finish(&mut export).unwrap();
+    for filepath in [&csv, &ndjson, &json] {
+        assert_transfers_eq(&read_transfers_file(filepath).unwrap(), &dataset);
+    }
+
+    // cryo named exports compress like parquet chunks
+    let mut transfer = Transfer::new();
+    transfer.compress(&csv).unwrap();
+    assert_transfers_eq(&decode(&transfer.output_filepath), &dataset);
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn exports_take_hex_integers_any_case_hex_and_binary_amounts() {
+    let directory = scratch("ingestion_hex");
+    let hash = format!("0X{}", "Ab".repeat(32));
+    let filepath = directory.join("transfers.csv");
+    fs::write(&filepath, format!(
+        "block_number,transaction_index,log_index,transaction_hash,erc20,from_address,to_address,value_binary,chain_id\n\
+         0x3e8,0,7,{},{},0x{},0x{},0x{:064x},1\n",
+        hash, "AA".repeat(20), "bB".repeat(20), "cc".repeat(20), 1_000_000u64,
+    )).unwrap();
+
+    let read = read_transfers_file(&filepath).unwrap();
+    assert_eq!(read.column("block_number").unwrap().u32().unwrap().get(0), Some(1_000));
+    assert_eq!(read.column("transaction_hash").unwrap().str().unwrap().get(0), Some(format!("0x{}", "ab".repeat(32)).as_str()));
+    assert_eq!(read.column("erc20").unwrap().str().unwrap().get(0), Some(format!("0x{}", "aa".repeat(20)).as_str()));
+    assert_eq!(read.column("value_string").unwrap().str().unwrap().get(0), Some("1000000"));
+
+    fs::write(&filepath, "block_number,transaction_index\n1,2\n").unwrap();
+    assert!(matches!(read_transfers_file(&filepath), Err(BladeError::Schema(_))));
+    fs::remove_dir_all(directory).unwrap();
+}
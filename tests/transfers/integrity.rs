@@ -0,0 +1,60 @@
+// Checksums of every stream and of the manifest, verified on read and by fsck.
+
+use std::fs::{self, File};
+use blade::transfers::integrity::fsck_directory;
+use blade::transfers::manifest::MANIFEST_CHECKSUM_COLUMN;
+use blade::transfers::options::CompressOptions;
+use blade::transfers::transfers::Transfer;
+use crate::common::{archive, scratch, transfers};
+use polars::prelude::*;
+
+#[test]
+fn fsck_finds_damaged_archives_in_subdirectories() {
+    let directory = scratch("fsck");
+    let nested = directory.join("ethereum").join("erc20_transfers");
+    fs::create_dir_all(&nested).unwrap();
+    let archive = archive(&nested, &transfers(1_000, 1_999, false), 1_000, 1_999, &CompressOptions::new());
+    assert!(fsck_directory(&directory).unwrap().is_empty());
+
+    // flip one byte of the stream data; the parquet footer stays readable
+    let mut bytes = fs::read(&archive).unwrap();
+    bytes[2_000] ^= 0xff;
+    fs::write(&archive, bytes).unwrap();
+    let damaged = fsck_directory(&directory).unwrap();
+    assert_eq!(damaged.len(), 1);
+    assert_eq!(damaged[0].0, archive);
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn archives_without_a_manifest_checksum_are_refused() {
+    let directory = scratch("checksum");
+    let archive = archive(&directory, &transfers(1_000, 1_999, false), 1_000, 1_999, &CompressOptions::new());
+    let mut stripped = ParquetReader::new(File::open(&archive).unwrap()).finish().unwrap()
+        .drop(MANIFEST_CHECKSUM_COLUMN).unwrap();
+    ParquetWriter::new(File::create(&archive).unwrap()).finish(&mut stripped).unwrap();
+
+    assert!(Transfer::new().decompress(&archive).is_err());
+    assert!(Transfer::new().decompress_projection(&archive, &["block_number".to_string()]).is_err());
+    assert_eq!(fsck_directory(&directory).unwrap().len(), 1);
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn fsck_reports_truncated_archives() {
+    let directory = scratch("fsck_truncated");
+    let options = CompressOptions::new();
+    let named = archive(&directory, &transfers(1_000, 1_999, false), 1_000, 1_999, &options);
+    let renamed = directory.join("transfers_2000_to_2999.parquet");
+    fs::rename(archive(&directory, &transfers(2_000, 2_999, false), 2_000, 2_999, &options), &renamed).unwrap();
+    assert!(fsck_directory(&directory).unwrap().is_empty());
+
+    // cut off the parquet footer, under the default name and under another name
+    for filepath in [&named, &renamed] {
+        let bytes = fs::read(filepath).unwrap();
+        fs::write(filepath, &bytes[..bytes.len() / 2]).unwrap();
+    }
+    let damaged: Vec<_> = fsck_directory(&directory).unwrap().into_iter().map(|(filepath, _)| filepath).collect();
+    assert_eq!(damaged, vec![named, renamed]);
+    fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,34 @@
+// Transaction hashes stored once per (block_number, transaction_index) key.
+
+use blade::transfers::compression::{
+    KeyedCompressedTransactionHashSeries, RLECompressedBlockNumberSeries, RLECompressedTransactionIndexSeries,
+};
+use crate::common::{streams, transfers};
+use polars::prelude::*;
+
+#[test]
+fn keyed_transaction_hash_round_trip() {
+    let dataset = transfers(1_000, 1_300, false);
+    let keyed = KeyedCompressedTransactionHashSeries::new().create_compressed_df(&dataset).unwrap()
+        .expect("(block_number, transaction_index) determines the hash");
+    let encoded = streams(dataset.height(), &[
+        keyed,
+        RLECompressedBlockNumberSeries::new().create_compressed_df(&dataset).unwrap(),
+        RLECompressedTransactionIndexSeries::new().create_compressed_df(&dataset).unwrap(),
+    ]);
+    let decoded = KeyedCompressedTransactionHashSeries::new().decompress(&encoded).unwrap();
+    assert!(decoded.equals_missing(dataset.column("transaction_hash").unwrap()));
+}
+
+#[test]
+fn keyed_transaction_hash_declines_ambiguous_keys() {
+    let dataset = df!(
+        "block_number" => [1u32, 1],
+        "transaction_index" => [0u32, 0],
+        "transaction_hash" => [format!("0x{}", "11".repeat(32)), format!("0x{}", "22".repeat(32))],
+    ).unwrap();
+    assert!(KeyedCompressedTransactionHashSeries::new().create_compressed_df(&dataset).unwrap().is_none());
+
+    // null keys cannot be looked up either
+    assert!(KeyedCompressedTransactionHashSeries::new().create_compressed_df(&transfers(1_000, 1_300, true)).unwrap().is_none());
+}
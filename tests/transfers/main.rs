@@ -0,0 +1,29 @@
+// Integration tests of the transfers archive, one module per feature, sharing
+// the synthetic datasets of `common`.
+
+mod common;
+mod scan;
+mod decoders;
+mod record_batch;
+mod append;
+mod compaction;
+mod integrity;
+mod errors;
+mod parallel;
+mod huffman;
+mod amounts;
+mod tokens;
+mod vocabulary;
+mod keyed_hashes;
+mod row_order;
+mod registry;
+mod bundle;
+mod in_memory;
+mod records;
+mod pipes;
+mod output;
+mod ingestion;
+mod export;
+mod projection;
+mod analytics;
+mod nulls;
@@ -0,0 +1,101 @@
+// Nulls in any column are encoded and restored by every codec.
+
+use std::fs;
+use blade::transfers::compression::{
+    DictionaryCompressedAddressSeries, DictionaryCompressedErc20Series, DictionaryCompressedTransactionHashSeries,
+    HotDictionaryCompressedValueStrings, MantissaExponentCompressedValueStrings, RLECompressedBlockNumberSeries,
+    RLECompressedChainIdSeries, RLECompressedErc20Series, RLECompressedLogIndexSeries, RLECompressedTransactionHashSeries,
+    RLECompressedTransactionIndexSeries, SharedVocabularyCompressedAddressSeries,
+};
+use blade::transfers::options::CompressOptions;
+use crate::common::{archive, assert_transfers_eq, decode, scratch, streams, transfers, with_nulls};
+
+#[test]
+fn run_length_columns_round_trip_nulls() {
+    let dataset = with_nulls(&transfers(1_000, 1_300, false));
+    let rows = dataset.height();
+
+    let blocks = streams(rows, &[RLECompressedBlockNumberSeries::new().create_compressed_df(&dataset).unwrap()]);
+    let decoded = RLECompressedBlockNumberSeries::new().decompress(&blocks).unwrap();
+    assert!(decoded.equals_missing(dataset.column("block_number").unwrap()));
+
+    let indexes = streams(rows, &[RLECompressedTransactionIndexSeries::new().create_compressed_df(&dataset).unwrap()]);
+    let decoded = RLECompressedTransactionIndexSeries::new().decompress(&indexes).unwrap();
+    assert!(decoded.equals_missing(dataset.column("transaction_index").unwrap()));
+
+    let logs = streams(rows, &[RLECompressedLogIndexSeries::new().create_compressed_df(&dataset).unwrap()]);
+    let decoded = RLECompressedLogIndexSeries::new().decompress(&logs).unwrap();
+    assert!(decoded.equals_missing(dataset.column("log_index").unwrap()));
+
+    let chain_ids = streams(rows, &[RLECompressedChainIdSeries::new().create_compressed_df(&dataset).unwrap()]);
+    let decoded = RLECompressedChainIdSeries::new().decompress(&chain_ids).unwrap();
+    assert!(decoded.equals_missing(dataset.column("chain_id").unwrap()));
+
+    let tokens = streams(rows, &[RLECompressedErc20Series::new().create_compressed_df(&dataset).unwrap()]);
+    let decoded = RLECompressedErc20Series::new().decompress(&tokens).unwrap();
+    assert!(decoded.equals_missing(dataset.column("erc20").unwrap()));
+}
+
+#[test]
+fn transaction_hashes_round_trip_nulls() {
+    let dataset = with_nulls(&transfers(1_000, 1_300, false));
+    let rows = dataset.height();
+
+    let runs = streams(rows, &[RLECompressedTransactionHashSeries::new().create_compressed_df(&dataset).unwrap()]);
+    let decoded = RLECompressedTransactionHashSeries::new().decompress(&runs).unwrap();
+    assert!(decoded.equals_missing(dataset.column("transaction_hash").unwrap()));
+
+    let dictionary = streams(rows, &DictionaryCompressedTransactionHashSeries::new().create_compressed_df(&dataset).unwrap());
+    let decoded = DictionaryCompressedTransactionHashSeries::new().decompress(&dictionary).unwrap();
+    assert!(decoded.equals_missing(dataset.column("transaction_hash").unwrap()));
+}
+
+#[test]
+fn addresses_round_trip_nulls() {
+    // many rows take a pair dictionary, a short chunk of unique pairs is raw
+    for dataset in [with_nulls(&transfers(1_000, 3_000, false)), with_nulls(&transfers(1_000, 1_010, false))] {
+        let rows = dataset.height();
+        let pairs = streams(rows, &DictionaryCompressedAddressSeries::new().create_compressed_df(&dataset).unwrap());
+        let (from, to) = DictionaryCompressedAddressSeries::new().decompress(&pairs).unwrap();
+        assert!(from.equals_missing(dataset.column("from_address").unwrap()));
+        assert!(to.equals_missing(dataset.column("to_address").unwrap()));
+
+        let tokens = streams(rows, &DictionaryCompressedErc20Series::new().create_compressed_df(&dataset).unwrap());
+        let decoded = DictionaryCompressedErc20Series::new().decompress(&tokens).unwrap();
+        assert!(decoded.equals_missing(dataset.column("erc20").unwrap()));
+
+        let vocabulary = streams(rows, &SharedVocabularyCompressedAddressSeries::new().create_compressed_df(&dataset).unwrap());
+        for column in ["erc20", "from_address", "to_address"] {
+            let decoded = SharedVocabularyCompressedAddressSeries::new().decompress(&vocabulary, column).unwrap();
+            assert!(decoded.equals_missing(dataset.column(column).unwrap()), "column `{}` differs", column);
+        }
+    }
+}
+
+#[test]
+fn values_round_trip_nulls() {
+    let dataset = with_nulls(&transfers(1_000, 1_300, false));
+    let rows = dataset.height();
+
+    let literals = streams(rows, &MantissaExponentCompressedValueStrings::new().create_compressed_df(&dataset).unwrap());
+    let decoded = MantissaExponentCompressedValueStrings::new().decompress(&literals).unwrap();
+    assert!(decoded.equals_missing(dataset.column("value_string").unwrap()));
+
+    // hot amounts are looked up through the erc20 column, nulls included
+    let mut values = HotDictionaryCompressedValueStrings::new();
+    values.compress(&dataset).unwrap();
+    let mut encoded = values.dataframes().unwrap();
+    encoded.push(RLECompressedErc20Series::new().create_compressed_df(&dataset).unwrap());
+    let decoded = HotDictionaryCompressedValueStrings::new().decompress(&streams(rows, &encoded)).unwrap();
+    assert!(decoded.equals_missing(dataset.column("value_string").unwrap()));
+}
+
+#[test]
+fn archive_round_trips_nulls() {
+    let directory = scratch("nulls");
+    for (start, dataset) in [(1_000, with_nulls(&transfers(1_000, 1_999, false))), (2_000, transfers(2_000, 2_999, true))] {
+        let archive = archive(&directory, &dataset, start, start + 999, &CompressOptions::new());
+        assert_transfers_eq(&decode(&archive), &dataset);
+    }
+    fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,65 @@
+// Where archives are written: output directory, naming template, overwrite
+// policy, and directory trees compressed in batch.
+
+use std::fs;
+use blade::transfers::options::CompressOptions;
+use blade::transfers::output::{compress_directory, OutputOptions, OverwritePolicy};
+use blade::transfers::transfers::Transfer;
+use crate::common::{assert_transfers_eq, decode, scratch, transfers, write_chunk};
+
+#[test]
+fn archives_follow_the_naming_template_and_overwrite_policy() {
+    let directory = scratch("output");
+    let chunk = write_chunk(&directory, &transfers(1_000, 1_500, false), 1_000, 1_500);
+    let mut output = OutputOptions::new();
+    output.directory = Some(directory.join("archives"));
+    output.naming = "{chain}__{dataset}__{start}_to_{end}.blade".to_string();
+
+    let compress = |output: &OutputOptions| {
+        let mut transfer = Transfer::new();
+        transfer.output = output.clone();
+        transfer.compress_into(&chunk, None)
+    };
+    let archive = compress(&output).unwrap().unwrap();
+    assert_eq!(archive, directory.join("archives/ethereum__erc20_transfers__1000_to_1500.blade"));
+    assert_transfers_eq(&decode(&archive), &transfers(1_000, 1_500, false));
+
+    // an existing archive fails by default, is skipped or replaced when asked to
+    assert!(compress(&output).is_err());
+    output.overwrite = OverwritePolicy::Skip;
+    assert!(compress(&output).unwrap().is_none());
+    output.overwrite = OverwritePolicy::Replace;
+    assert_eq!(compress(&output).unwrap(), Some(archive));
+
+    // the input is never written over
+    output.directory = None;
+    output.naming = "{file}".to_string();
+    let input = fs::read(&chunk).unwrap();
+    assert!(compress(&output).is_err());
+    assert_eq!(fs::read(&chunk).unwrap(), input);
+    assert!("append".parse::<OverwritePolicy>().is_err());
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn directories_compress_into_a_mirrored_tree() {
+    let directory = scratch("output_batch");
+    fs::create_dir_all(directory.join("data/2020")).unwrap();
+    write_chunk(&directory.join("data"), &transfers(1_000, 1_199, false), 1_000, 1_199);
+    write_chunk(&directory.join("data/2020"), &transfers(1_200, 1_399, false), 1_200, 1_399);
+
+    let mut output = OutputOptions::new();
+    output.directory = Some(directory.join("archives"));
+    output.mirror = true;
+    let written = compress_directory(&directory.join("data"), &CompressOptions::new(), &output).unwrap();
+    assert_eq!(written, vec![
+        directory.join("archives/2020/BLADE_ethereum__erc20_transfers__1200_to_1399.parquet"),
+        directory.join("archives/BLADE_ethereum__erc20_transfers__1000_to_1199.parquet"),
+    ]);
+    assert_transfers_eq(&decode(&written[0]), &transfers(1_200, 1_399, false));
+
+    // a second run skips the existing archives
+    output.overwrite = OverwritePolicy::Skip;
+    assert!(compress_directory(&directory.join("data"), &CompressOptions::new(), &output).unwrap().is_empty());
+    fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,30 @@
+// Columns and large address chunks are compressed in parallel; the archive is
+// the same bytes whatever the number of threads.
+
+use blade::transfers::compression::DictionaryCompressedAddressSeries;
+use blade::transfers::transfers::Transfer;
+use crate::common::{assert_transfers_eq, streams, transfers};
+
+#[test]
+fn parallel_compression_matches_a_single_thread() {
+    // more rows than one chunk of the address dictionary
+    let dataset = transfers(1, 40_000, false);
+    assert!(dataset.height() > 65_536);
+
+    let parallel = Transfer::new().compress_to_bytes(&dataset).unwrap();
+    let sequential = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap()
+        .install(|| Transfer::new().compress_to_bytes(&dataset).unwrap());
+    assert_eq!(parallel, sequential);
+    assert_transfers_eq(&Transfer::new().decompress_bytes(&parallel).unwrap(), &dataset);
+
+    // merged chunk dictionaries give ids in order of first occurrence
+    let encoded = streams(dataset.height(), &DictionaryCompressedAddressSeries::new().create_compressed_df(&dataset).unwrap());
+    let (from, to) = DictionaryCompressedAddressSeries::new().decompress(&encoded).unwrap();
+    assert!(from.equals_missing(dataset.column("from_address").unwrap()));
+    assert!(to.equals_missing(dataset.column("to_address").unwrap()));
+    let mut next = 0;
+    for id in encoded.get("address_index").unwrap().u32().unwrap().into_no_null_iter() {
+        assert!(id <= next, "id {} appears before id {}", id, next);
+        next = next.max(id + 1);
+    }
+}
@@ -0,0 +1,35 @@
+// Archives compressed from a byte stream, eg: stdin, into any sink, eg: stdout.
+
+use std::fs::{self, File};
+use blade::transfers::transfers::Transfer;
+use crate::common::{assert_transfers_eq, scratch, transfers, write_chunk};
+use polars::prelude::*;
+
+#[test]
+fn compress_reader_sniffs_parquet_and_csv() {
+    let dataset = transfers(1_000, 1_500, false);
+    let mut parquet: Vec<u8> = Vec::new();
+    ParquetWriter::new(&mut parquet).finish(&mut dataset.clone()).unwrap();
+    let mut csv: Vec<u8> = Vec::new();
+    CsvWriter::new(&mut csv).finish(&mut dataset.drop_many(["value_binary", "value_f64"])).unwrap();
+
+    for input in [parquet, csv] {
+        let mut archive: Vec<u8> = Vec::new();
+        Transfer::new().compress_reader(input.as_slice(), &mut archive).unwrap();
+        assert_transfers_eq(&Transfer::new().decompress_reader(archive.as_slice()).unwrap(), &dataset);
+    }
+}
+
+#[test]
+fn compress_file_to_writer_matches_compress() {
+    let directory = scratch("pipes");
+    let dataset = transfers(1_000, 1_500, false);
+    let chunk = write_chunk(&directory, &dataset, 1_000, 1_500);
+    let mut transfer = Transfer::new();
+    transfer.compress(&chunk).unwrap();
+
+    let piped = directory.join("piped.blade");
+    Transfer::new().compress_file_to_writer(&chunk, File::create(&piped).unwrap()).unwrap();
+    assert_eq!(fs::read(&piped).unwrap(), fs::read(&transfer.output_filepath).unwrap());
+    fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,25 @@
+// Decoding a subset of columns from the streams they need.
+
+use blade::error::BladeError;
+use blade::transfers::options::CompressOptions;
+use blade::transfers::transfers::Transfer;
+use crate::common::{assert_transfers_eq, transfers};
+
+#[test]
+fn projection_matches_full_decode() {
+    let dataset = transfers(1_000, 2_000, false);
+    let mut reordered = Transfer::new();
+    reordered.options = CompressOptions::new();
+    reordered.options.reorder_rows = true;
+    for bytes in [Transfer::new().compress_to_bytes(&dataset).unwrap(), reordered.compress_to_bytes(&dataset).unwrap()] {
+        let full = Transfer::new().decompress_bytes(&bytes).unwrap();
+        for columns in [vec!["transaction_hash"], vec!["value_string", "to_address"], vec!["block_number", "erc20"]] {
+            let columns: Vec<String> = columns.into_iter().map(str::to_string).collect();
+            let projected = Transfer::new().decompress_bytes_projection(&bytes, &columns).unwrap();
+            assert_eq!(projected.width(), columns.len());
+            assert_transfers_eq(&projected, &full);
+        }
+        let unknown = Transfer::new().decompress_bytes_projection(&bytes, &["gas_used".to_string()]);
+        assert!(matches!(unknown, Err(BladeError::Schema(_))));
+    }
+}
@@ -0,0 +1,35 @@
+// Arrow RecordBatches in and out: compress from batches or a batch reader, and
+// read decoded transfers back as batches in the schema they were compressed from.
+
+use std::fs;
+use blade::transfers::record_batch::{dataframe_to_record_batch, record_batches_to_dataframe};
+use blade::transfers::transfers::Transfer;
+use arrow::record_batch::RecordBatchReader;
+use crate::common::{assert_transfers_eq, scratch, transfers};
+
+#[test]
+fn record_batches_round_trip() {
+    let directory = scratch("record_batch");
+    let dataset = transfers(1_000, 1_999, false);
+    let batches: Vec<_> = (0..dataset.height()).step_by(500)
+        .map(|offset| dataframe_to_record_batch(&dataset.slice(offset as i64, 500)).unwrap())
+        .collect();
+    let archive = directory.join("transfers.blade");
+    Transfer::new().compress_record_batches(&batches, &archive).unwrap();
+
+    // batches of at most 128 rows, in the schema compress takes
+    let reader = Transfer::new().decompress_record_batches(&archive, 128).unwrap();
+    assert_eq!(reader.schema(), batches[0].schema());
+    let read: Vec<_> = reader.collect::<Result<_, _>>().unwrap();
+    assert!(read.iter().all(|batch| batch.num_rows() <= 128 && batch.schema() == batches[0].schema()));
+    assert_transfers_eq(&record_batches_to_dataframe(&read).unwrap(), &dataset);
+
+    // batches read back compress again, through a batch reader
+    let reader = Transfer::new().decompress_record_batches(&archive, 300).unwrap();
+    let mut bytes: Vec<u8> = Vec::new();
+    Transfer::new().compress_record_batch_reader(reader, &mut bytes).unwrap();
+    assert_transfers_eq(&Transfer::new().decompress_bytes(&bytes).unwrap(), &dataset);
+
+    assert!(Transfer::new().decompress_record_batches(&archive, 0).is_err());
+    fs::remove_dir_all(directory).unwrap();
+}
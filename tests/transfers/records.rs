@@ -0,0 +1,146 @@
+// Typed TransferRecords read from archives range by range, serialized, and
+// compressed back; archives decoded in ranges of rows.
+
+use std::fs;
+use blade::transfers::chunks::{ArchiveChunks, CHUNK_ROWS};
+use blade::transfers::compression::*;
+use blade::transfers::manifest::{Manifest, StreamEncoding};
+use blade::transfers::options::CompressOptions;
+use blade::transfers::record::{transfer_record_schema, TransferRecord};
+use blade::transfers::row_order::{compression_order, RowOrder};
+use blade::transfers::transfers::Transfer;
+use crate::common::{archive, assert_transfers_eq, concat, decode, scratch, streams, transfers};
+use polars::prelude::*;
+
+#[test]
+fn records_round_trip() {
+    let directory = scratch("records");
+    let dataset = transfers(1_000, 2_000, false);
+    let filepath = archive(&directory, &dataset, 1_000, 2_000, &CompressOptions::new());
+
+    let records: Vec<TransferRecord> = Transfer::new().decompress_records(&filepath).unwrap()
+        .collect::<Result<_, _>>().unwrap();
+    assert_eq!(records.len(), dataset.height());
+    let hashes = dataset.column("transaction_hash").unwrap().str().unwrap();
+    let values = dataset.column("value_string").unwrap().str().unwrap();
+    for (row, record) in records.iter().enumerate() {
+        assert_eq!(format!("0x{}", hex::encode(record.transaction_hash)), hashes.get(row).unwrap());
+        assert_eq!(record.value.to_string(), values.get(row).unwrap());
+    }
+
+    // serialized as cryo's strings and back
+    let json = serde_json::to_string(&records[0]).unwrap();
+    assert!(json.contains(hashes.get(0).unwrap()));
+    assert_eq!(serde_json::from_str::<TransferRecord>(&json).unwrap(), records[0]);
+    let schema = serde_json::to_value(transfer_record_schema()).unwrap();
+    assert!(schema["properties"]["transaction_hash"].is_object());
+
+    let mut bytes: Vec<u8> = Vec::new();
+    Transfer::new().compress_records(records, &mut bytes).unwrap();
+    assert_transfers_eq(&Transfer::new().decompress_bytes(&bytes).unwrap(), &decode(&filepath));
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn records_refuse_null_values() {
+    let directory = scratch("records_nulls");
+    let dataset = transfers(1_000, 1_100, true);
+    let filepath = archive(&directory, &dataset, 1_000, 1_100, &CompressOptions::new());
+    let results: Vec<_> = Transfer::new().decompress_records(&filepath).unwrap().collect();
+    assert!(results[7].is_err() && results[6].is_ok());
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn chunks_match_the_full_decode() {
+    let directory = scratch("records_chunks");
+    let mut options = CompressOptions::new();
+    options.entropy_coding = StreamEncoding::Huffman;
+    for (i, null_blocks) in [false, true].into_iter().enumerate() {
+        let start = 1_000 * (i as u32 + 1);
+        let dataset = transfers(start, start + 600, null_blocks);
+        let filepath = archive(&directory, &dataset, start, start + 600, &options);
+        let decoded = decode(&filepath);
+        let columns = decoded.get_column_names_owned().iter().map(|name| name.to_string()).collect::<Vec<_>>();
+
+        // ranges cut runs and keyed transactions anywhere
+        for chunk_rows in [1, 97, decoded.height()] {
+            let chunks: Vec<DataFrame> = Transfer::new().decompress_chunks(&filepath, &columns, chunk_rows).unwrap()
+                .collect::<Result<_, _>>().unwrap();
+            assert!(chunks.iter().all(|chunk| chunk.height() <= chunk_rows));
+            assert_transfers_eq(&concat(&chunks.iter().collect::<Vec<_>>()), &decoded);
+        }
+    }
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn chunks_of_every_codec_match_the_full_decode() {
+    for null_blocks in [false, true] {
+        // packed hash and token ids, address pairs, hot amounts, rows reordered within blocks
+        let dataset = transfers(1_000, 1_600, null_blocks);
+        let positions = compression_order(&dataset).unwrap();
+        let stored = dataset.take(&IdxCa::from_vec("".into(), positions.iter().map(|p| *p as IdxSize).collect())).unwrap();
+        let mut order = RowOrder::new();
+        order.extend(&positions);
+        let mut values = HotDictionaryCompressedValueStrings::new();
+        values.compress(&stored).unwrap();
+
+        let mut manifest = Manifest::new("transfers");
+        manifest.rows = stored.height();
+        manifest.add_column("block_number", "rle", &[RLECompressedBlockNumberSeries::new().create_compressed_df(&stored).unwrap()]).unwrap();
+        manifest.add_column("transaction_index", "rle", &[RLECompressedTransactionIndexSeries::new().create_compressed_df(&stored).unwrap()]).unwrap();
+        manifest.add_column("log_index", "rle", &[RLECompressedLogIndexSeries::new().create_compressed_df(&stored).unwrap()]).unwrap();
+        manifest.add_column("transaction_hash", "dictionary", &DictionaryCompressedTransactionHashSeries::new().create_compressed_df(&stored).unwrap()).unwrap();
+        manifest.add_column("erc20", "dictionary", &DictionaryCompressedErc20Series::new().create_compressed_df(&stored).unwrap()).unwrap();
+        let addresses = DictionaryCompressedAddressSeries::new().create_compressed_df(&stored).unwrap();
+        manifest.add_column("from_address", "dictionary", &addresses).unwrap();
+        manifest.add_column("to_address", "dictionary", &addresses).unwrap();
+        manifest.add_column("value_string", "hot_dictionary", &values.dataframes().unwrap()).unwrap();
+        manifest.add_column("chain_id", "rle", &[RLECompressedChainIdSeries::new().create_compressed_df(&stored).unwrap()]).unwrap();
+        let encoded = [
+            vec![
+                RLECompressedBlockNumberSeries::new().create_compressed_df(&stored).unwrap(),
+                RLECompressedTransactionIndexSeries::new().create_compressed_df(&stored).unwrap(),
+                RLECompressedLogIndexSeries::new().create_compressed_df(&stored).unwrap(),
+                RLECompressedChainIdSeries::new().create_compressed_df(&stored).unwrap(),
+                order.create_compressed_df().unwrap(),
+            ],
+            DictionaryCompressedTransactionHashSeries::new().create_compressed_df(&stored).unwrap(),
+            DictionaryCompressedErc20Series::new().create_compressed_df(&stored).unwrap(),
+            addresses,
+            values.dataframes().unwrap(),
+        ].concat();
+
+        let mut transfer = Transfer::new();
+        transfer.manifest = manifest.clone();
+        let columns = manifest.column_names();
+        let decoded = transfer.decompress_columns(&streams(stored.height(), &encoded), &columns).unwrap();
+        assert_transfers_eq(&decoded, &dataset);
+        for chunk_rows in [1, 97, 1_000] {
+            let chunks: Vec<DataFrame> = ArchiveChunks::new(manifest.clone(), streams(stored.height(), &encoded), &columns, chunk_rows).unwrap()
+                .collect::<Result<_, _>>().unwrap();
+            assert!(chunks.len() > 1);
+            assert_transfers_eq(&concat(&chunks.iter().collect::<Vec<_>>()), &dataset);
+        }
+    }
+}
+
+#[test]
+fn records_are_decoded_range_by_range() {
+    let directory = scratch("records_ranges");
+    let dataset = transfers(1, 30_000, false);
+    assert!(dataset.height() > CHUNK_ROWS);
+    let filepath = archive(&directory, &dataset, 1, 30_000, &CompressOptions::new());
+
+    let records = Transfer::new().decompress_records(&filepath).unwrap();
+    assert_eq!(records.size_hint(), (dataset.height(), Some(dataset.height())));
+    let values = dataset.column("value_string").unwrap().str().unwrap();
+    let mut rows = 0;
+    for (row, record) in records.enumerate() {
+        assert_eq!(record.unwrap().value.to_string(), values.get(row).unwrap());
+        rows += 1;
+    }
+    assert_eq!(rows, dataset.height());
+    fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,34 @@
+// Address ids shared across archives through a persistent registry.
+
+use std::fs;
+use blade::transfers::compression::SharedVocabularyCompressedAddressSeries;
+use blade::transfers::options::CompressOptions;
+use blade::transfers::registry::REGISTRY_STREAM;
+use crate::common::{archive, assert_transfers_eq, decode, scratch, streams, transfers};
+use polars::prelude::*;
+
+#[test]
+fn address_vocabulary_round_trip_against_registry() {
+    let dataset = transfers(1_000, 1_300, false);
+    let registry: Vec<String> = dataset.column("from_address").unwrap().str().unwrap()
+        .into_no_null_iter().take(50).map(|address| address[2..].to_string()).collect();
+
+    let mut encoded = streams(dataset.height(), &SharedVocabularyCompressedAddressSeries::with_registry(&registry).create_compressed_df(&dataset).unwrap());
+    encoded.insert(Column::new(REGISTRY_STREAM.into(), &registry));
+    for column in ["erc20", "from_address", "to_address"] {
+        let decoded = SharedVocabularyCompressedAddressSeries::new().decompress(&encoded, column).unwrap();
+        assert!(decoded.equals_missing(dataset.column(column).unwrap()), "column `{}` differs", column);
+    }
+}
+
+#[test]
+fn archives_share_an_address_registry() {
+    let directory = scratch("registry");
+    let mut options = CompressOptions::new();
+    options.address_registry = Some(directory.join("addresses.registry"));
+    let first = archive(&directory, &transfers(1_000, 1_999, false), 1_000, 1_999, &options);
+    let second = archive(&directory, &transfers(2_000, 2_999, false), 2_000, 2_999, &options);
+    assert_transfers_eq(&decode(&first), &transfers(1_000, 1_999, false));
+    assert_transfers_eq(&decode(&second), &transfers(2_000, 2_999, false));
+    fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,40 @@
+// Rows stored sorted within blocks, cryo's order restored from the stored permutation.
+
+use blade::transfers::options::CompressOptions;
+use blade::transfers::row_order::{compression_order, RowOrder};
+use blade::transfers::transfers::Transfer;
+use crate::common::{assert_transfers_eq, streams, transfers};
+use polars::prelude::*;
+
+#[test]
+fn row_order_permutes_and_restores() {
+    let dataset = transfers(1_000, 1_300, false);
+    let positions = compression_order(&dataset).unwrap();
+    let stored = dataset.take(&IdxCa::from_vec("".into(), positions.iter().map(|p| *p as IdxSize).collect())).unwrap();
+    assert!(!stored.equals_missing(&dataset));
+
+    let mut order = RowOrder::new();
+    order.extend(&positions);
+    let encoded = streams(dataset.height(), &[order.create_compressed_df().unwrap()]);
+    let loaded = RowOrder::load(&encoded).unwrap().expect("row order streams");
+    assert_eq!(loaded.positions(), positions);
+    assert!(loaded.restore(&stored).unwrap().equals_missing(&dataset));
+
+    // a row order must be a permutation of the stored rows
+    let mut broken = order.clone();
+    broken.starts[0] = broken.starts[1];
+    assert!(broken.restore(&stored).is_err());
+}
+
+#[test]
+fn archive_round_trips_with_reordered_rows() {
+    let mut options = CompressOptions::new();
+    options.reorder_rows = true;
+    for null_blocks in [false, true] {
+        let dataset = transfers(1_000, 2_000, null_blocks);
+        let mut transfer = Transfer::new();
+        transfer.options = options.clone();
+        let bytes = transfer.compress_to_bytes(&dataset).unwrap();
+        assert_transfers_eq(&Transfer::new().decompress_bytes(&bytes).unwrap(), &dataset);
+    }
+}
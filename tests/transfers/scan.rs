@@ -0,0 +1,53 @@
+// Lazy scans of an archive: projections, predicates and slices pushed into the decoders.
+
+use std::fs;
+use blade::transfers::options::CompressOptions;
+use blade::transfers::scan::scan_blade;
+use crate::common::{archive, assert_transfers_eq, decode, scratch, transfers};
+use polars::prelude::*;
+
+#[test]
+fn scan_matches_full_decode() {
+    let directory = scratch("scan");
+    let dataset = transfers(1_000, 1_999, false);
+    let archive = archive(&directory, &dataset, 1_000, 1_999, &CompressOptions::new());
+    let decoded = decode(&archive);
+
+    // cryo's schema, value_binary / value_f64 derived from value_string
+    let scanned = scan_blade(&archive).unwrap().collect().unwrap();
+    assert_eq!(scanned.get_column_names(), dataset.get_column_names());
+    assert_transfers_eq(&scanned, &dataset);
+
+    // a projection in its own order
+    let projected = scan_blade(&archive).unwrap().select([col("value_string"), col("block_number")]).collect().unwrap();
+    assert_eq!(projected.get_column_names(), ["value_string", "block_number"]);
+    assert_transfers_eq(&projected, &decoded);
+
+    // a predicate on one column, projecting others
+    let filtered = scan_blade(&archive).unwrap()
+        .filter(col("block_number").gt_eq(lit(1_250u32)).and(col("block_number").lt(lit(1_500u32))))
+        .select([col("transaction_hash"), col("to_address")])
+        .collect().unwrap();
+    let expected = decoded.lazy()
+        .filter(col("block_number").gt_eq(lit(1_250u32)).and(col("block_number").lt(lit(1_500u32))))
+        .select([col("transaction_hash"), col("to_address")])
+        .collect().unwrap();
+    assert!(filtered.height() > 0);
+    assert!(filtered.equals_missing(&expected));
+
+    // derived columns projected and filtered on
+    let derived = scan_blade(&archive).unwrap()
+        .filter(col("value_f64").gt(lit(1e18)))
+        .select([col("value_binary"), col("value_f64"), col("erc20")])
+        .collect().unwrap();
+    let expected = dataset.clone().lazy()
+        .filter(col("value_f64").gt(lit(1e18)))
+        .select([col("value_binary"), col("value_f64"), col("erc20")])
+        .collect().unwrap();
+    assert!(derived.height() > 0);
+    assert!(derived.equals_missing(&expected));
+
+    let head = scan_blade(&archive).unwrap().limit(7).collect().unwrap();
+    assert_eq!(head.height(), 7);
+    fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,52 @@
+// Token contracts as bit-packed dictionary ids, continued across appends.
+
+use blade::transfers::compression::bit_pack::{bit_width, pack, packed_words, unpack};
+use blade::transfers::compression::DictionaryCompressedErc20Series;
+use crate::common::{concat, streams, transfers};
+use polars::prelude::*;
+
+#[test]
+fn erc20_dictionary_round_trip() {
+    let dataset = transfers(1_000, 1_300, false);
+    let encoded = streams(dataset.height(), &DictionaryCompressedErc20Series::new().create_compressed_df(&dataset).unwrap());
+    assert!(encoded.contains("token_ids_packed"));
+    let decoded = DictionaryCompressedErc20Series::new().decompress(&encoded).unwrap();
+    assert!(decoded.equals_missing(dataset.column("erc20").unwrap()));
+
+    // a single token packs its ids at width 0
+    let single = df!("erc20" => vec!["0x00000000000000000000000000000000000000aa"; 5]).unwrap();
+    let encoded = streams(5, &DictionaryCompressedErc20Series::new().create_compressed_df(&single).unwrap());
+    let decoded = DictionaryCompressedErc20Series::new().decompress(&encoded).unwrap();
+    assert!(decoded.equals_missing(single.column("erc20").unwrap()));
+}
+
+#[test]
+fn erc20_dictionary_continues_after_load() {
+    let first = transfers(1_000, 1_100, false);
+    let second = transfers(1_101, 1_200, false);
+    let encoded = streams(first.height(), &DictionaryCompressedErc20Series::new().create_compressed_df(&first).unwrap());
+
+    let mut tokens = DictionaryCompressedErc20Series::new();
+    tokens.load(&encoded).unwrap();
+    let appended = streams(first.height() + second.height(), &tokens.create_compressed_df(&second).unwrap());
+    let decoded = DictionaryCompressedErc20Series::new().decompress(&appended).unwrap();
+    let expected = concat(&[&first, &second]);
+    assert!(decoded.equals_missing(expected.column("erc20").unwrap()));
+}
+
+#[test]
+fn bit_pack_round_trip_at_every_width() {
+    for width in 0..=32u8 {
+        let max = if width == 32 { u32::MAX } else { (1u32 << width) - 1 };
+        let values: Vec<u32> = (0..257u32).map(|i| i.wrapping_mul(2_654_435_761) & max).collect();
+        assert!(values.iter().all(|value| bit_width(*value) <= width));
+
+        let words = pack(&values, width);
+        assert_eq!(words.len(), packed_words(values.len(), width));
+        assert_eq!(unpack(&words, width, values.len()).unwrap(), values);
+        if !words.is_empty() {
+            assert!(unpack(&words[..words.len() - 1], width, values.len()).is_err());
+        }
+    }
+    assert!(unpack(&[0], 33, 1).is_err());
+}
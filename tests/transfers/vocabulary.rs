@@ -0,0 +1,14 @@
+// Token, sender and receiver ids into one shared address vocabulary.
+
+use blade::transfers::compression::SharedVocabularyCompressedAddressSeries;
+use crate::common::{streams, transfers};
+
+#[test]
+fn address_vocabulary_round_trip() {
+    let dataset = transfers(1_000, 1_300, false);
+    let encoded = streams(dataset.height(), &SharedVocabularyCompressedAddressSeries::new().create_compressed_df(&dataset).unwrap());
+    for column in ["erc20", "from_address", "to_address"] {
+        let decoded = SharedVocabularyCompressedAddressSeries::new().decompress(&encoded, column).unwrap();
+        assert!(decoded.equals_missing(dataset.column(column).unwrap()), "column `{}` differs", column);
+    }
+}